@@ -0,0 +1,186 @@
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use kdbplus::*;
+use kdbplus::api::*;
+use super::{ContentsBuffer, load_binary_data, catch_panic, ComtradeError};
+use super::config::deserialize_comtrade_config_contents;
+use super::data::{DataSpec, deserialize_comtrade_data_contents};
+use super::header::deserialize_comtrade_header_contents;
+use super::info::deserialize_comtrade_info_contents;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Prefix of a line separating sections of `.cff` file.
+pub(crate) const SECTION_PREFIX: &[u8] = b"--- file type:";
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Struct                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Contents of each section of `.cff` file.
+#[derive(Default)]
+pub(crate) struct CffSections<'a>{
+  pub(crate) config: Option<&'a [u8]>,
+  pub(crate) info: Option<&'a [u8]>,
+  pub(crate) header: Option<&'a [u8]>,
+  pub(crate) data: Option<&'a [u8]>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Private Fucntions                           //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Find the end of a line starting at `cursor`. Returns the end of the line contents and the start of the next line.
+fn find_line_end(bytes: &[u8], cursor: usize) -> (usize, usize){
+  match bytes[cursor..].iter().position(|byte| *byte == b'\n'){
+    Some(offset) => {
+      let end = cursor + offset;
+      if end > cursor && bytes[end-1] == b'\r'{
+        (end-1, end+1)
+      }
+      else{
+        (end, end+1)
+      }
+    },
+    None => (bytes.len(), bytes.len())
+  }
+}
+
+/// Find the start of the next section separator at or after `cursor`.
+fn find_next_section(bytes: &[u8], mut cursor: usize) -> usize{
+  while cursor < bytes.len(){
+    if bytes[cursor..].starts_with(SECTION_PREFIX){
+      return cursor;
+    }
+    cursor = find_line_end(bytes, cursor).1;
+  }
+  bytes.len()
+}
+
+/// Split contents of `.cff` file into sections.
+/// Separator format: "--- file type: CFG ---", "--- file type: DAT BINARY: 1234 ---".
+/// The byte count following ':' is mandatory for a binary data section.
+pub(crate) fn split_comtrade_cff(bytes: &[u8]) -> Result<CffSections<'_>, &'static str>{
+  let mut sections = CffSections::default();
+  let mut cursor = find_next_section(bytes, 0);
+  while cursor < bytes.len(){
+    let (line_end, contents_start) = find_line_end(bytes, cursor);
+    let separator = match std::str::from_utf8(&bytes[cursor + SECTION_PREFIX.len()..line_end]){
+      Ok(separator) => separator,
+      Err(_) => return Err("invalid section separator\0")
+    };
+    let separator = match separator.trim().strip_suffix("---"){
+      Some(separator) => separator.trim(),
+      None => return Err("invalid section separator - missing '---'\0")
+    };
+    // "DAT BINARY: 1234" -> ("DAT BINARY", Some("1234"))
+    let (file_type, byte_count) = match separator.split_once(':'){
+      Some((file_type, byte_count)) => (file_type, Some(byte_count.trim())),
+      None => (separator, None)
+    };
+    let contents_end = match byte_count{
+      Some(byte_count) => match byte_count.parse::<usize>(){
+        Ok(num) if contents_start + num <= bytes.len() => contents_start + num,
+        Ok(_) => return Err("early EOF\0"),
+        Err(_) => return Err("invalid byte count of section\0")
+      },
+      None => find_next_section(bytes, contents_start)
+    };
+    let contents = &bytes[contents_start..contents_end];
+    match file_type.split_whitespace().next().unwrap_or("").to_ascii_uppercase().as_str(){
+      "CFG" => sections.config = Some(contents),
+      "INF" => sections.info = Some(contents),
+      "HDR" => sections.header = Some(contents),
+      "DAT" => sections.data = Some(contents),
+      _ => return Err("invalid section file type\0")
+    }
+    cursor = find_next_section(bytes, contents_end);
+  }
+  Ok(sections)
+}
+
+/// Convert contents of a text section into `&str`.
+pub(crate) fn section_to_str(bytes: &[u8]) -> Result<&str, &'static str>{
+  match std::str::from_utf8(bytes){
+    Ok(string) => Ok(string),
+    Err(_) => Err("invalid UTF-8 section\0")
+  }
+}
+
+/// Deserialize contents of `.cff` file into q dictionary.
+/// Errors in a section are located by the section name.
+fn deserialize_comtrade_cff_contents(bytes: &[u8]) -> Result<K, ComtradeError>{
+  let sections = split_comtrade_cff(bytes)?;
+
+  let config = match sections.config{
+    Some(contents) => deserialize_comtrade_config_contents(section_to_str(contents)?).map_err(|error| error.in_file("CFG section"))?,
+    None => return Err("missing CFG section\0".into())
+  };
+
+  let data = match sections.data{
+    Some(contents) => {
+      match DataSpec::from_config(config).map_err(ComtradeError::from).and_then(|spec| deserialize_comtrade_data_contents(contents, &spec)){
+        Ok(data) => data,
+        Err(error) => {
+          decrement_reference_count(config);
+          return Err(error.in_file("DAT section"));
+        }
+      }
+    },
+    None => {
+      decrement_reference_count(config);
+      return Err("missing DAT section\0".into());
+    }
+  };
+
+  // Information and header are optional.
+  let info = match section_to_str(sections.info.unwrap_or(b"")).map_err(ComtradeError::from).and_then(deserialize_comtrade_info_contents){
+    Ok(info) => info,
+    Err(error) => {
+      decrement_reference_count(config);
+      decrement_reference_count(data);
+      return Err(error.in_file("INF section"));
+    }
+  };
+  let header = deserialize_comtrade_header_contents(sections.header.unwrap_or(b""));
+
+  let keys = new_list(qtype::SYMBOL_LIST, 4);
+  let keys_slice = keys.as_mut_slice::<S>();
+  keys_slice[0]=internalize(str_to_S!("config"));
+  keys_slice[1]=internalize(str_to_S!("info"));
+  keys_slice[2]=internalize(str_to_S!("header"));
+  keys_slice[3]=internalize(str_to_S!("data"));
+  let values = new_list(qtype::COMPOUND_LIST, 4);
+  values.as_mut_slice::<K>().copy_from_slice(&[config, info, header, data]);
+  Ok(new_dictionary(keys, values))
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Interface                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Deserialize the combined file (`.cff`) of COMTRADE format into q dictionary with keys `config`, `info`, `header`
+///  and `data`.
+/// # Parameters
+/// - `data`: 
+///   - symbol: File path which starts with `:`.
+///   - string: File contents.
+///   - list of byte: File contents
+#[no_mangle]
+pub extern "C" fn deserialize_comtrade_cff(data: K) -> K{
+  catch_panic(||{
+    let bytes;
+    let mut contents_buffer = ContentsBuffer::default();
+    // Load data into bytes.
+    set_bytes!(data, bytes, contents_buffer);
+
+    match deserialize_comtrade_cff_contents(bytes){
+      Ok(cff) => cff,
+      Err(error) => error.in_source(data).to_q_error()
+    }
+  })
+}
@@ -5,18 +5,28 @@
 use kdbplus::*;
 use kdbplus::api::*;
 use chrono::prelude::*;
-use super::{parse_token, KDB_TIMESTAMP_OFFSET, load_ascii_data};
+use super::{parse_token, KDB_TIMESTAMP_OFFSET, ContentsBuffer, load_ascii_data, write_data, find_dictionary_value, get_dictionary_value, check_config_list, get_config_list, update_dictionary, split_lines, catch_panic, ComtradeError};
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                          Private Fucntions                           //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+/// Remove the last character of a token, e.g. 'A' of "6A". `None` is returned for an empty token.
+fn trim_last_char(token: &str) -> Option<&str>{
+  let mut chars = token.chars();
+  chars.next_back()?;
+  Some(chars.as_str())
+}
+
 /// Deserialize 1st component (line) of `.cfg` file.
-/// Format: "station_name,rec_dev_id,rev_year".
-fn deserialize_comtrade_config_1(lines: &Vec<&str>, cursor: usize) -> Result<(K, K, usize), &'static str>{
-  let tokens=lines[cursor].split(',').collect::<Vec<&str>>();
-  if tokens.len() != 3{
-    Err("the number of fields is fewer than expected -  line1\0")
+/// Format: "station_name,rec_dev_id,rev_year". "rev_year" is missing in the 1991 revision.
+fn deserialize_comtrade_config_1(lines: &Vec<&str>, cursor: usize) -> Result<(K, K, i32, usize), ComtradeError>{
+  let tokens=match lines.get(cursor){
+    Some(line) => line.split(',').collect::<Vec<&str>>(),
+    None => return Err("early EOF\0".into())
+  };
+  if tokens.len() != 2 && tokens.len() != 3{
+    Err("the number of fields is fewer than expected\0".into())
   }
   else{
     let keys = new_list(qtype::SYMBOL_LIST, 3);
@@ -24,15 +34,16 @@ fn deserialize_comtrade_config_1(lines: &Vec<&str>, cursor: usize) -> Result<(K,
     keys_slice[0]=internalize(str_to_S!("station_name"));
     keys_slice[1]=internalize(str_to_S!("recording_device_id"));
     keys_slice[2]=internalize(str_to_S!("revision_year"));
+    let revision_year = tokens.get(2).and_then(|token| token.parse::<i32>().ok()).unwrap_or(1991);
     let values=new_list(qtype::COMPOUND_LIST, 3);
-    values.as_mut_slice::<K>().copy_from_slice(&[new_symbol(tokens[0]), new_symbol(tokens[1]), new_int(tokens[2].parse::<i32>().unwrap_or_else(|_| 1991))]);
-    Ok((keys, values, cursor + 1))
+    values.as_mut_slice::<K>().copy_from_slice(&[new_symbol(tokens[0]), new_symbol(tokens[1]), new_int(revision_year)]);
+    Ok((keys, values, revision_year, cursor + 1))
   }
 }
 
 /// Deserialize 2nd component (line) of `.cfg` file.
 /// Format: "TT,##A,##D".
-fn deserialize_comtrade_config_2(lines: &Vec<&str>, cursor: usize) -> Result<(K, K, i32, i32, usize), &'static str>{
+fn deserialize_comtrade_config_2(lines: &Vec<&str>, cursor: usize) -> Result<(K, K, i32, i32, usize), ComtradeError>{
   let keys = new_list(qtype::SYMBOL_LIST, 3);
   let keys_slice=keys.as_mut_slice::<S>();
   keys_slice[0]=internalize(str_to_S!("total_number_of_channels"));
@@ -44,11 +55,12 @@ fn deserialize_comtrade_config_2(lines: &Vec<&str>, cursor: usize) -> Result<(K,
     tokens=line.split(',').collect::<Vec<&str>>();
   }
   else{
-    return Err("early EOF\0");
+    decrement_reference_count(keys);
+    return Err("early EOF\0".into());
   }
   if tokens.len() != 3{
     decrement_reference_count(keys);
-    Err("the number of fields is fewer than expected -  line2\0")
+    Err("the number of fields is fewer than expected\0".into())
   }
   else{
     let values=new_list(qtype::COMPOUND_LIST, 3);
@@ -60,31 +72,31 @@ fn deserialize_comtrade_config_2(lines: &Vec<&str>, cursor: usize) -> Result<(K,
       Err(_) => {
         decrement_reference_count(keys);
         decrement_reference_count(values);
-        return Err("invalid total number of channels\0")
+        return Err(ComtradeError::from("invalid total number of channels\0").at_field(0, tokens[0]))
       }
     }
     // Trim last 'A'
-    match tokens[1][..tokens[1].len()-1].parse::<i32>(){
-      Ok(num) => {
+    match trim_last_char(tokens[1]).map(str::parse::<i32>){
+      Some(Ok(num)) if num >= 0 => {
         num_analog_channel=num;
         values_slice[1]=new_int(num)
       },
-      Err(_) => {
+      _ => {
         decrement_reference_count(keys);
         decrement_reference_count(values);
-        return Err("invalid number of analog channels\0")
+        return Err(ComtradeError::from("invalid number of analog channels\0").at_field(1, tokens[1]))
       }
     }
     // Trim last 'D'
-    match tokens[2][..tokens[2].len()-1].parse::<i32>(){
-      Ok(num) => {
+    match trim_last_char(tokens[2]).map(str::parse::<i32>){
+      Some(Ok(num)) if num >= 0 => {
         num_status_channel=num;
         values_slice[2]=new_int(num)
       },
-      Err(_) => {
+      _ => {
         decrement_reference_count(keys);
         decrement_reference_count(values);
-        return Err("invalid number of status channels\0")
+        return Err(ComtradeError::from("invalid number of status channels\0").at_field(2, tokens[2]))
       }
     }
     Ok((keys, values, num_analog_channel, num_status_channel, cursor+1))
@@ -93,10 +105,32 @@ fn deserialize_comtrade_config_2(lines: &Vec<&str>, cursor: usize) -> Result<(K,
 
 /// Deserialize each line of 3rd component of `.cfg` file.
 /// Format: "An,ch_id,ph,ccbm,uu,a,b,skew,min,max,primary,secondary,PS".
-fn deserialize_comtrade_config_3_inner(line: &str, values: K) -> Result<(), &'static str>{
+/// The 1991 revision can omit "primary,secondary,PS", which are filled as null.
+fn deserialize_comtrade_config_3_inner(line: &str, values: K, revision_year: i32) -> Result<(), ComtradeError>{
   let tokens=line.split(',').collect::<Vec<&str>>();
-  if tokens.len() != 13{
-    Err("the number of fields is fewer than expected -  component 3\0")
+  if revision_year == 1991 && tokens.len() == 10{
+    let values_slice=values.as_mut_slice::<K>();
+
+    parse_token![i32; tokens, 0, values_slice, 0, "invalid analog channel index\0"];
+
+    for i in 1..5{
+      values_slice[i].push_symbol(tokens[i]).unwrap();
+    }
+
+    parse_token![f64; tokens, 5, values_slice, 5, "invalid channel multiplier\0"];
+    parse_token![f64; tokens, 6, values_slice, 6, "invalid channel offset adder\0"];
+    parse_token![f64; tokens, 7, values_slice, 7, "invalid channel skew\0"];
+    parse_token![i32; tokens, 8, values_slice, 8, "invalid minimum value\0"];
+    parse_token![i32; tokens, 9, values_slice, 9, "invalid maximum value\0"];
+
+    values_slice[10].push_raw(qnull_base::E).unwrap();
+    values_slice[11].push_raw(qnull_base::E).unwrap();
+    values_slice[12].push_raw(' ').unwrap();
+
+    Ok(())
+  }
+  else if tokens.len() != 13{
+    Err("the number of fields is fewer than expected\0".into())
   }
   else{
     let values_slice=values.as_mut_slice::<K>();
@@ -122,7 +156,7 @@ fn deserialize_comtrade_config_3_inner(line: &str, values: K) -> Result<(), &'st
       "s" | "S" => {
         values_slice[12].push_raw('s').unwrap();
       },
-      _ => return Err("invalid data scaling identifier\0")
+      _ => return Err(ComtradeError::from("invalid data scaling identifier\0").at_field(12, tokens[12]))
     }
     
     Ok(())
@@ -130,11 +164,11 @@ fn deserialize_comtrade_config_3_inner(line: &str, values: K) -> Result<(), &'st
 }
 
 /// Deserialize 3rd line of `.cfg` file.
-fn deserialize_comtrade_config_3(lines: &Vec<&str>, num_analog_channel: i32, cursor: usize) -> Result<(K, K, usize), &'static str>{
+fn deserialize_comtrade_config_3(lines: &Vec<&str>, num_analog_channel: i32, revision_year: i32, cursor: usize) -> Result<(K, K, usize), ComtradeError>{
   
   if lines.len() < cursor + num_analog_channel as usize{
     // There are fewer lines than expected
-    Err("early EOF\0")
+    Err("early EOF\0".into())
   }
   else{
     let keys = new_list(qtype::SYMBOL_LIST, 13);
@@ -171,10 +205,10 @@ fn deserialize_comtrade_config_3(lines: &Vec<&str>, num_analog_channel: i32, cur
       new_string("")
     ]);
 
-    let result=lines[cursor..(cursor+num_analog_channel as usize)].iter().map(|line|
+    let result=lines[cursor..(cursor+num_analog_channel as usize)].iter().enumerate().map(|(idx, line)|
       // Deserialize each line and append new values to corresponding lists.
-      deserialize_comtrade_config_3_inner(*line, values)
-    ).collect::<Result<Vec<_>, &'static str>>();
+      deserialize_comtrade_config_3_inner(*line, values, revision_year).map_err(|error| error.at_line(cursor + idx + 1))
+    ).collect::<Result<Vec<_>, ComtradeError>>();
     
     match result{
       Ok(_) => Ok((keys, values, cursor+num_analog_channel as usize)),
@@ -190,10 +224,15 @@ fn deserialize_comtrade_config_3(lines: &Vec<&str>, num_analog_channel: i32, cur
 
 /// Deserialize each line of 4th component of `.cfg` file.
 /// Format: "Dn,ch_id,ph,ccbm,y".
-fn deserialize_comtrade_config_4_inner(line: &str, values: K) -> Result<(), &'static str>{
-  let tokens=line.split(',').collect::<Vec<&str>>();
+/// The 1991 revision can omit "ph,ccbm", which are filled as null.
+fn deserialize_comtrade_config_4_inner(line: &str, values: K, revision_year: i32) -> Result<(), ComtradeError>{
+  let mut tokens=line.split(',').collect::<Vec<&str>>();
+  if revision_year == 1991 && tokens.len() == 3{
+    // "Dn,ch_id,y" -> "Dn,ch_id,,,y"
+    tokens.splice(2..2, ["", ""].iter().cloned());
+  }
   if tokens.len() != 5{
-    Err("the number of fields is fewer than expected -  component 4\0")
+    Err("the number of fields is fewer than expected\0".into())
   }
   else{
     let values_slice=values.as_mut_slice::<K>();
@@ -211,7 +250,7 @@ fn deserialize_comtrade_config_4_inner(line: &str, values: K) -> Result<(), &'st
       "1" => {
         values_slice[4].push_raw(1_u8).unwrap();
       },
-      _ => return Err("invalid channel state\0")
+      _ => return Err(ComtradeError::from("invalid channel state\0").at_field(4, tokens[4]))
     }
 
     Ok(())
@@ -219,11 +258,11 @@ fn deserialize_comtrade_config_4_inner(line: &str, values: K) -> Result<(), &'st
 }
 
 /// Deserialize 4th line of `.cfg` file.
-fn deserialize_comtrade_config_4(lines: &Vec<&str>, num_status_channel: i32, cursor: usize) -> Result<(K, K, usize), &'static str>{
+fn deserialize_comtrade_config_4(lines: &Vec<&str>, num_status_channel: i32, revision_year: i32, cursor: usize) -> Result<(K, K, usize), ComtradeError>{
   
   if lines.len() < cursor+num_status_channel as usize{
     // There are fewer lines than expected
-    Err("early EOF\0")
+    Err("early EOF\0".into())
   }
   else{
     let keys = new_list(qtype::SYMBOL_LIST, 5);
@@ -243,10 +282,10 @@ fn deserialize_comtrade_config_4(lines: &Vec<&str>, num_status_channel: i32, cur
       new_list(qtype::BOOL_LIST, 0_i64)
     ]);
 
-    let result=lines[cursor..cursor+num_status_channel as usize].iter().map(|line|
+    let result=lines[cursor..cursor+num_status_channel as usize].iter().enumerate().map(|(idx, line)|
       // Deserialize each line and append new values to corresponding lists.
-      deserialize_comtrade_config_4_inner(*line, values)
-    ).collect::<Result<Vec<_>, &'static str>>();
+      deserialize_comtrade_config_4_inner(*line, values, revision_year).map_err(|error| error.at_line(cursor + idx + 1))
+    ).collect::<Result<Vec<_>, ComtradeError>>();
     
     match result{
       Ok(_) => Ok((keys, values, cursor + num_status_channel as usize)),
@@ -261,23 +300,24 @@ fn deserialize_comtrade_config_4(lines: &Vec<&str>, num_status_channel: i32, cur
 
 /// Deserialize 5th component (line) of `.cfg` file.
 /// Format: "lf".
-fn deserialize_comtrade_config_5(lines: &Vec<&str>, mut keys: K, mut values: K, cursor: usize) -> Result<usize, &'static str>{
+fn deserialize_comtrade_config_5(lines: &Vec<&str>, mut keys: K, mut values: K, cursor: usize) -> Result<(K, K, usize), ComtradeError>{
   if let Some(line) = lines.get(cursor){
-    keys.push_symbol("line_frequency").unwrap();
-    values.push(new_real(line.parse::<f64>().unwrap_or_else(|_| qnull_base::F))).unwrap();
-    Ok(cursor + 1)
+    // Can be reallocated after push
+    keys = keys.push_symbol("line_frequency").unwrap();
+    values = values.push(new_real(line.parse::<f64>().unwrap_or(qnull_base::F))).unwrap();
+    Ok((keys, values, cursor + 1))
   }
   else{
-    Err("early EOF\0")
+    Err("early EOF\0".into())
   }
 }
 
 /// Deserialize each line of sample rate in the 6th component of `.cfg` file.
 /// "samp,endsamp"
-fn deserialize_comtrade_config_6_inner(line: &str, values: K) -> Result<(), &'static str>{
+fn deserialize_comtrade_config_6_inner(line: &str, values: K) -> Result<(), ComtradeError>{
   let tokens=line.split(',').collect::<Vec<&str>>();
   if tokens.len() != 2{
-    Err("the number of fields is fewer than expected -  component 6\0")
+    Err("the number of fields is fewer than expected\0".into())
   }
   else{
     let values_slice=values.as_mut_slice::<K>();
@@ -292,11 +332,11 @@ fn deserialize_comtrade_config_6_inner(line: &str, values: K) -> Result<(), &'st
 /// "samp,endsamp"
 /// ...
 /// "samp,endsamp"
-fn deserialize_comtrade_config_6(lines: &Vec<&str>, cursor: usize) -> Result<(K, K, usize), &'static str>{
+fn deserialize_comtrade_config_6(lines: &Vec<&str>, cursor: usize) -> Result<(K, K, usize), ComtradeError>{
   match lines.get(cursor){
     Some(line) => {
       match line.parse::<i32>(){
-        Ok(num) if lines.len() >= cursor + 1 + num as usize=> {
+        Ok(num) if num >= 0 && lines.len() >= cursor + 1 + num as usize=> {
           // Line of sample rate exits even if num is 0.
           // Length check is done further in the code below.
 
@@ -317,17 +357,17 @@ fn deserialize_comtrade_config_6(lines: &Vec<&str>, cursor: usize) -> Result<(K,
             if let Some(line) = lines.get(cursor+1){
               match deserialize_comtrade_config_6_inner(*line, values){
                 Ok(_) => Ok((keys, values, cursor + 1 + 1)),
-                Err(error) => Err(error)
+                Err(error) => Err(error.at_line(cursor + 2))
               }
             }
             else{
-              Err("early EOF\0")
+              Err("early EOF\0".into())
             }
           }
           else{
-            let result=lines[cursor+1..cursor+1+num as usize].iter().map(|line|{
-              deserialize_comtrade_config_6_inner(*line, values)
-            }).collect::<Result<Vec<_>, &'static str>>();
+            let result=lines[cursor+1..cursor+1+num as usize].iter().enumerate().map(|(idx, line)|{
+              deserialize_comtrade_config_6_inner(*line, values).map_err(|error| error.at_line(cursor + idx + 2))
+            }).collect::<Result<Vec<_>, ComtradeError>>();
 
             match result{
               Ok(_) => Ok((keys, values, cursor + 1 + num as usize)),
@@ -335,11 +375,11 @@ fn deserialize_comtrade_config_6(lines: &Vec<&str>, cursor: usize) -> Result<(K,
             }
           }
         },
-        Ok(_) => Err("early EOF\0"),
-        _ => Err("invalid number of sample rates\0")
+        Ok(num) if num >= 0 => Err("early EOF\0".into()),
+        _ => Err(ComtradeError::from("invalid number of sample rates\0").with_token(line))
       }
     },
-    None => Err("early EOF\0")
+    None => Err("early EOF\0".into())
   }
 }
 
@@ -347,9 +387,16 @@ fn deserialize_comtrade_config_6(lines: &Vec<&str>, cursor: usize) -> Result<(K,
 /// "dd/mm/yyyy,hh:mm:ss.ssssss"
 fn deserialize_comtrade_config_7_inner(line: &str, error: &'static str) -> Result<i64, &'static str>{
   if line.len() == 26{
+    if !line.is_ascii(){
+      // Fields cannot be sliced at fixed positions.
+      return Err(error);
+    }
     match (line[0..2].parse::<u32>(), line[3..5].parse::<u32>(), line[6..10].parse::<i32>(), line[11..13].parse::<u32>(), line[14..16].parse::<u32>(), line[17..19].parse::<u32>(), line[20..26].parse::<u32>()){
       (Ok(day), Ok(month), Ok(year), Ok(hour), Ok(minute), Ok(second), Ok(micros)) => {
-        Ok(Utc.ymd(year, month, day).and_hms_micro(hour, minute, second, micros).timestamp_nanos() - KDB_TIMESTAMP_OFFSET)
+        match NaiveDate::from_ymd_opt(year, month, day).and_then(|date| date.and_hms_micro_opt(hour, minute, second, micros)){
+          Some(datetime) => Ok(Utc.from_utc_datetime(&datetime).timestamp_nanos() - KDB_TIMESTAMP_OFFSET),
+          None => Err(error)
+        }
       },
       _ => Err(error)
     }
@@ -361,26 +408,70 @@ fn deserialize_comtrade_config_7_inner(line: &str, error: &'static str) -> Resul
   
 }
 
+/// Deserialize each line of 7th component of `.cfg` file in the 1991 revision.
+/// "mm/dd/yy,hh:mm:ss.ssssss"
+/// Four digit year and fraction of second with any number of digits up to nanosecond are also accepted.
+fn deserialize_comtrade_config_7_inner_1991(line: &str, error: &'static str) -> Result<i64, &'static str>{
+  if line.trim().is_empty(){
+    // Field is non-critical. Fill as null.
+    return Ok(qnull_base::J);
+  }
+  let (date, time) = match line.split_once(','){
+    Some(date_time) => date_time,
+    None => return Err(error)
+  };
+  let date = date.trim().split('/').collect::<Vec<&str>>();
+  let (time, fraction) = time.trim().split_once('.').unwrap_or((time.trim(), "0"));
+  let time = time.split(':').collect::<Vec<&str>>();
+  if date.len() != 3 || time.len() != 3 || fraction.is_empty() || fraction.len() > 9{
+    return Err(error);
+  }
+  match (date[0].parse::<u32>(), date[1].parse::<u32>(), date[2].parse::<i32>(), time[0].parse::<u32>(), time[1].parse::<u32>(), time[2].parse::<u32>(), fraction.parse::<u32>()){
+    (Ok(month), Ok(day), Ok(year), Ok(hour), Ok(minute), Ok(second), Ok(fraction_)) => {
+      let year = match date[2].len(){
+        // Two digit year
+        2 if year < 70 => 2000 + year,
+        2 => 1900 + year,
+        _ => year
+      };
+      let nanos = fraction_ * 10_u32.pow(9 - fraction.len() as u32);
+      match NaiveDate::from_ymd_opt(year, month, day).and_then(|date| date.and_hms_nano_opt(hour, minute, second, nanos)){
+        Some(datetime) => Ok(Utc.from_utc_datetime(&datetime).timestamp_nanos() - KDB_TIMESTAMP_OFFSET),
+        None => Err(error)
+      }
+    },
+    _ => Err(error)
+  }
+}
+
 /// Deserialize 7th component of `.cfg` file.
 /// "dd/mm/yyyy,hh:mm:ss.ssssss"
 /// "dd/mm/yyyy,hh:mm:ss.ssssss"
-fn deserialize_comtrade_config_7(lines: &Vec<&str>, mut keys: K, mut values: K, cursor: usize) -> Result<usize, &'static str>{
+fn deserialize_comtrade_config_7(lines: &Vec<&str>, mut keys: K, mut values: K, revision_year: i32, cursor: usize) -> Result<(K, K, usize), ComtradeError>{
   if lines.len() < cursor + 2{
     // There are fewer lines than expected
-    Err("early EOF\0")
+    Err("early EOF\0".into())
   }
   else{
-    keys.push_symbol("first_data_time").unwrap();
-    keys.push_symbol("event_time").unwrap();
-    values.push(new_timestamp(deserialize_comtrade_config_7_inner(lines[cursor], "invalid first data time\0")?)).unwrap();
-    values.push(new_timestamp(deserialize_comtrade_config_7_inner(lines[cursor+1], "invalid event time\0")?)).unwrap();
-    Ok(cursor+2)
+    let deserialize_time = if revision_year == 1991 {deserialize_comtrade_config_7_inner_1991} else {deserialize_comtrade_config_7_inner};
+    // Deserialize both times before pushing so that lists are not modified on error.
+    let mut times = [0; 2];
+    for (idx, message) in ["invalid first data time\0", "invalid event time\0"].iter().enumerate(){
+      let line = lines[cursor+idx];
+      times[idx] = deserialize_time(line, message).map_err(|error| ComtradeError::from(error).at_line(cursor + idx + 1).with_token(line))?;
+    }
+    // Can be reallocated after push
+    keys = keys.push_symbol("first_data_time").unwrap();
+    keys = keys.push_symbol("event_time").unwrap();
+    values = values.push(new_timestamp(times[0])).unwrap();
+    values = values.push(new_timestamp(times[1])).unwrap();
+    Ok((keys, values, cursor+2))
   }
 }
 
 /// Deserialize 8th component (line) of `.cfg` file.
 /// Format: "ft".
-fn deserialize_comtrade_config_8(lines: &Vec<&str>, cursor: usize) -> Result<(&'static str, usize), &'static str>{
+fn deserialize_comtrade_config_8(lines: &Vec<&str>, cursor: usize) -> Result<(&'static str, usize), ComtradeError>{
   if let Some(line) = lines.get(cursor){
     match *line{
       "ASCII" | "ascii" => {
@@ -389,61 +480,143 @@ fn deserialize_comtrade_config_8(lines: &Vec<&str>, cursor: usize) -> Result<(&'
       "BINARY" | "binary" => {
         Ok(("binary", cursor+1))
       },
-      _ => Err("invalid file type\0")
+      "BINARY32" | "binary32" => {
+        Ok(("binary32", cursor+1))
+      },
+      "FLOAT32" | "float32" => {
+        Ok(("float32", cursor+1))
+      },
+      _ => Err(ComtradeError::from("invalid file type\0").with_token(line))
     }
   }
   else{
-    Err("early EOF\0")
+    Err("early EOF\0".into())
   }
 }
 
-/// Deserialize 8th component (line) of `.cfg` file.
-/// Format: "ft".
-fn deserialize_comtrade_config_9(lines: &Vec<&str>, mut keys: K, mut values: K, cursor: usize) -> Result<usize, &'static str>{
-  if let Some(line) = lines.get(cursor){
+/// Deserialize 9th component (line) of `.cfg` file.
+/// Format: "timemult".
+/// This component does not exist in the 1991 revision and 1.0 is filled.
+fn deserialize_comtrade_config_9(lines: &Vec<&str>, mut keys: K, mut values: K, revision_year: i32, cursor: usize) -> Result<(K, K, usize), ComtradeError>{
+  if revision_year == 1991 && cursor == lines.len(){
+    // Can be reallocated after push
+    keys = keys.push_symbol("timestamp_multiplication_factor").unwrap();
+    values = values.push(new_real(1.0)).unwrap();
+    Ok((keys, values, cursor))
+  }
+  else if let Some(line) = lines.get(cursor){
     match line.parse::<f64>(){
       Ok(num) => {
-        keys.push_symbol("timestamp_multiplication_factor").unwrap();
-        values.push(new_real(num)).unwrap();
-        Ok(cursor + 1)
+        // Can be reallocated after push
+        keys = keys.push_symbol("timestamp_multiplication_factor").unwrap();
+        values = values.push(new_real(num)).unwrap();
+        Ok((keys, values, cursor + 1))
       },
-      Err(_) => Err("invalid timestamp multiplication factor\0")
+      Err(_) => Err(ComtradeError::from("invalid timestamp multiplication factor\0").with_token(line))
     }
   }
   else{
-    Err("early EOF\0")
+    Err("early EOF\0".into())
   }
 }
 
-//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
-//                               Interface                              //
-//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+/// Deserialize a time zone offset in the 10th component of `.cfg` file into nanoseconds.
+/// Format: "[+|-]hh[hmm]" (e.g. "-5", "+10h30"). "x" or an empty field is filled as null.
+fn deserialize_comtrade_config_10_inner(token: &str, error: &'static str) -> Result<i64, &'static str>{
+  let token = token.trim();
+  if token.is_empty() || token == "x" || token == "X"{
+    // Field is non-critical. Fill as null.
+    return Ok(qnull_base::J);
+  }
+  let (sign, offset) = match token.strip_prefix('-'){
+    Some(offset) => (-1_i64, offset),
+    None => (1_i64, token.strip_prefix('+').unwrap_or(token))
+  };
+  let (hours, minutes) = match offset.split_once(['h', 'H']){
+    Some((hours, minutes)) => (hours.parse::<i64>(), minutes.parse::<i64>()),
+    None => (offset.parse::<i64>(), Ok(0))
+  };
+  match (hours, minutes){
+    (Ok(hours), Ok(minutes)) if (0..=24).contains(&hours) && (0..60).contains(&minutes) => {
+      Ok(sign * (hours * 3600 + minutes * 60) * 1_000_000_000)
+    },
+    _ => Err(error)
+  }
+}
 
-/// Deserialize the configuration file (`.cfg`) of COMTRADE format into q dictionary.
-/// # Parameters
-/// - `data`: 
-///   - symbol: File path which starts with `:`. Thsi file must be delimited <CR/LF>. i.e., the file must be in the Windows format.
-///   - string: File contents.
-#[no_mangle]
-pub extern "C" fn deserialize_comtrade_config(data: K) -> K{
+/// Deserialize 10th component (line) of `.cfg` file. This component exists only in the 2013 revision.
+/// Format: "time_code,local_code".
+fn deserialize_comtrade_config_10(lines: &Vec<&str>, mut keys: K, mut values: K, cursor: usize) -> Result<(K, K, usize), ComtradeError>{
+  if let Some(line) = lines.get(cursor){
+    let tokens=line.split(',').collect::<Vec<&str>>();
+    if tokens.len() != 2{
+      Err("the number of fields is fewer than expected\0".into())
+    }
+    else{
+      let time_code = deserialize_comtrade_config_10_inner(tokens[0], "invalid time code\0").map_err(|error| ComtradeError::from(error).at_field(0, tokens[0]))?;
+      let local_code = deserialize_comtrade_config_10_inner(tokens[1], "invalid local code\0").map_err(|error| ComtradeError::from(error).at_field(1, tokens[1]))?;
+      // Can be reallocated after push
+      keys = keys.push_symbol("time_code").unwrap();
+      keys = keys.push_symbol("local_code").unwrap();
+      values = values.push(new_timespan(time_code)).unwrap();
+      values = values.push(new_timespan(local_code)).unwrap();
+      Ok((keys, values, cursor + 1))
+    }
+  }
+  else{
+    Err("early EOF\0".into())
+  }
+}
 
-  let string;
-  let mut contents_buffer = String::new();
-  // Load data into string.
-  set_string!(data, string, contents_buffer);
+/// Deserialize 11th component (line) of `.cfg` file. This component exists only in the 2013 revision.
+/// Format: "tmq_code,leapsec".
+/// - `tmq_code`: Time quality code of the recording device as a hexadecimal digit (0-F).
+/// - `leapsec`: Leap second indicator (0: none, 1: added, 2: subtracted, 3: not capable).
+fn deserialize_comtrade_config_11(lines: &Vec<&str>, mut keys: K, mut values: K, cursor: usize) -> Result<(K, K, usize), ComtradeError>{
+  if let Some(line) = lines.get(cursor){
+    let tokens=line.split(',').collect::<Vec<&str>>();
+    if tokens.len() != 2{
+      Err("the number of fields is fewer than expected\0".into())
+    }
+    else{
+      let time_quality_code = match i32::from_str_radix(tokens[0].trim(), 16){
+        Ok(num) if (0..16).contains(&num) => num,
+        _ => return Err(ComtradeError::from("invalid time quality code\0").at_field(0, tokens[0]))
+      };
+      let leap_second = match tokens[1].trim().parse::<i32>(){
+        Ok(num) if (0..4).contains(&num) => num,
+        _ => return Err(ComtradeError::from("invalid leap second indicator\0").at_field(1, tokens[1]))
+      };
+      // Can be reallocated after push
+      keys = keys.push_symbol("time_quality_code").unwrap();
+      keys = keys.push_symbol("leap_second").unwrap();
+      values = values.push(new_int(time_quality_code)).unwrap();
+      values = values.push(new_int(leap_second)).unwrap();
+      Ok((keys, values, cursor + 1))
+    }
+  }
+  else{
+    Err("early EOF\0".into())
+  }
+}
 
-  let lines=string.split_terminator("\r\n").collect::<Vec<&str>>();
+/// Deserialize contents of the configuration file (`.cfg`) of COMTRADE format into q dictionary.
+pub(crate) fn deserialize_comtrade_config_contents(string: &str) -> Result<K, ComtradeError>{
+
+  let lines=split_lines(string);
   let mut keys;
   let mut values;
   let mut cursor = 0;
 
+  let revision_year;
   match deserialize_comtrade_config_1(&lines, cursor){
-    Ok((keys_, values_, cursor_)) => {
+    Ok((keys_, values_, revision_year_, cursor_)) => {
       cursor= cursor_;
+      revision_year = revision_year_;
       keys = keys_;
       values = values_;
     },
-    Err(error) => return new_error(error)
+    Err(error) => return Err(error.in_component(1).at_line(cursor + 1))
   }
 
   let num_analog_channel;
@@ -459,11 +632,11 @@ pub extern "C" fn deserialize_comtrade_config(data: K) -> K{
     Err(error) => {
       decrement_reference_count(keys);
       decrement_reference_count(values);
-      return new_error(error)
+      return Err(error.in_component(2).at_line(cursor + 1))
     }
   }
 
-  match deserialize_comtrade_config_3(&lines, num_analog_channel, cursor){
+  match deserialize_comtrade_config_3(&lines, num_analog_channel, revision_year, cursor){
     Ok((keys_, values_, cursor_)) => {
       cursor=cursor_;
       keys.append(keys_).unwrap();
@@ -472,11 +645,11 @@ pub extern "C" fn deserialize_comtrade_config(data: K) -> K{
     Err(error) => {
       decrement_reference_count(keys);
       decrement_reference_count(values);
-      return new_error(error)
+      return Err(error.in_component(3).at_line(cursor + 1))
     }
   }
 
-  match deserialize_comtrade_config_4(&lines, num_status_channel, cursor){
+  match deserialize_comtrade_config_4(&lines, num_status_channel, revision_year, cursor){
     Ok((keys_, values_, cursor_)) => {
       cursor=cursor_;
       keys.append(keys_).unwrap();
@@ -485,16 +658,21 @@ pub extern "C" fn deserialize_comtrade_config(data: K) -> K{
     Err(error) => {
       decrement_reference_count(keys);
       decrement_reference_count(values);
-      return new_error(error)
+      return Err(error.in_component(4).at_line(cursor + 1))
     }
   }
 
   match deserialize_comtrade_config_5(&lines, keys, values, cursor){
-    Ok(cursor_) => cursor=cursor_,
+    Ok((keys_, values_, cursor_)) => {
+      // Can be reallocated after push
+      cursor=cursor_;
+      keys=keys_;
+      values=values_;
+    },
     Err(error) => {
       decrement_reference_count(keys);
       decrement_reference_count(values);
-      return new_error(error)
+      return Err(error.in_component(5).at_line(cursor + 1))
     }
   }
 
@@ -507,18 +685,21 @@ pub extern "C" fn deserialize_comtrade_config(data: K) -> K{
     Err(error) => {
       decrement_reference_count(keys);
       decrement_reference_count(values);
-      return new_error(error)
+      return Err(error.in_component(6).at_line(cursor + 1))
     }
   }
 
-  match deserialize_comtrade_config_7(&lines, keys, values, cursor){
-    Ok(cursor_) => {
+  match deserialize_comtrade_config_7(&lines, keys, values, revision_year, cursor){
+    Ok((keys_, values_, cursor_)) => {
+      // Can be reallocated after push
       cursor=cursor_;
+      keys=keys_;
+      values=values_;
     },
     Err(error) => {
       decrement_reference_count(keys);
       decrement_reference_count(values);
-      return new_error(error)
+      return Err(error.in_component(7).at_line(cursor + 1))
     }
   }
 
@@ -532,25 +713,426 @@ pub extern "C" fn deserialize_comtrade_config(data: K) -> K{
     Err(error) => {
       decrement_reference_count(keys);
       decrement_reference_count(values);
-      return new_error(error)
+      return Err(error.in_component(8).at_line(cursor + 1))
     }
   }
 
-  match deserialize_comtrade_config_9(&lines, keys, values, cursor){
-    Ok(cursor_) => {
+  match deserialize_comtrade_config_9(&lines, keys, values, revision_year, cursor){
+    Ok((keys_, values_, cursor_)) => {
+      // Can be reallocated after push
       cursor=cursor_;
+      keys=keys_;
+      values=values_;
     },
     Err(error) => {
       decrement_reference_count(keys);
       decrement_reference_count(values);
-      return new_error(error)
+      return Err(error.in_component(9).at_line(cursor + 1))
+    }
+  }
+
+  if revision_year >= 2013{
+    // Time zone and time quality exist only in the 2013 revision.
+    match deserialize_comtrade_config_10(&lines, keys, values, cursor){
+      Ok((keys_, values_, cursor_)) => {
+        // Can be reallocated after push
+        cursor=cursor_;
+        keys=keys_;
+        values=values_;
+      },
+      Err(error) => {
+        decrement_reference_count(keys);
+        decrement_reference_count(values);
+        return Err(error.in_component(10).at_line(cursor + 1))
+      }
+    }
+
+    match deserialize_comtrade_config_11(&lines, keys, values, cursor){
+      Ok((keys_, values_, cursor_)) => {
+        // Can be reallocated after push
+        cursor=cursor_;
+        keys=keys_;
+        values=values_;
+      },
+      Err(error) => {
+        decrement_reference_count(keys);
+        decrement_reference_count(values);
+        return Err(error.in_component(11).at_line(cursor + 1))
+      }
     }
   }
 
   if cursor != lines.len(){
-    new_error("redundant line?\0")
+    decrement_reference_count(keys);
+    decrement_reference_count(values);
+    Err(ComtradeError::from("redundant line?\0").at_line(cursor + 1))
+  }
+  else{
+    Ok(new_dictionary(keys, values))
+  }
+}
+
+/// Get a value of the last occurrence of a given key from a configuration. `circuit_component_being_monitored` appears
+///  twice for analog channels and status channels.
+fn get_last_config_value(config: K, key: &str) -> Result<K, &'static str>{
+  // Check that the key exists.
+  get_dictionary_value(config, key)?;
+  let config_slice = config.as_mut_slice::<K>();
+  match config_slice[0].as_mut_slice::<S>().iter().rposition(|symbol| S_to_str(*symbol) == key){
+    Some(index) => Ok(config_slice[1].as_mut_slice::<K>()[index]),
+    None => Err("missing key in dictionary\0")
+  }
+}
+
+/// Check that a text field does not exceed `max_length` characters and does not contain a delimiter.
+fn serialize_comtrade_config_text<'a>(field: &'a str, max_length: usize, error: &'static str) -> Result<&'a str, &'static str>{
+  if field.chars().count() > max_length || field.contains([',', '\r', '\n']){
+    Err(error)
+  }
+  else{
+    Ok(field)
+  }
+}
+
+/// Serialize a real value. Null and infinity are rejected.
+fn serialize_comtrade_config_real(value: f32, error: &'static str) -> Result<String, &'static str>{
+  if value.is_finite(){
+    Ok(format!("{}", value))
+  }
+  else{
+    Err(error)
+  }
+}
+
+/// Serialize 1st component (line) of `.cfg` file.
+/// Format: "station_name,rec_dev_id,rev_year".
+fn serialize_comtrade_config_1(config: K, lines: &mut Vec<String>) -> Result<i32, &'static str>{
+  let station_name = get_dictionary_value(config, "station_name")?;
+  let recording_device_id = get_dictionary_value(config, "recording_device_id")?;
+  let revision_year = get_dictionary_value(config, "revision_year")?.get_int()?;
+  if revision_year != 1999 && revision_year != 2013{
+    return Err("unsupported revision year - expected 1999 or 2013\0");
+  }
+  lines.push(format!("{},{},{}",
+    serialize_comtrade_config_text(station_name.get_symbol()?, 64, "invalid station name\0")?,
+    serialize_comtrade_config_text(recording_device_id.get_symbol()?, 64, "invalid recording device id\0")?,
+    revision_year
+  ));
+  Ok(revision_year)
+}
+
+/// Serialize 2nd component (line) of `.cfg` file.
+/// Format: "TT,##A,##D".
+fn serialize_comtrade_config_2(config: K, lines: &mut Vec<String>) -> Result<(i32, i32), &'static str>{
+  let total_number_of_channels = get_dictionary_value(config, "total_number_of_channels")?.get_int()?;
+  let num_analog_channel = get_dictionary_value(config, "number_of_analog_channels")?.get_int()?;
+  let num_status_channel = get_dictionary_value(config, "number_of_status_channels")?.get_int()?;
+  if num_analog_channel < 0 || num_status_channel < 0 || total_number_of_channels != num_analog_channel + num_status_channel{
+    return Err("total number of channels does not match the number of analog and status channels\0");
+  }
+  lines.push(format!("{},{}A,{}D", total_number_of_channels, num_analog_channel, num_status_channel));
+  Ok((num_analog_channel, num_status_channel))
+}
+
+/// Serialize 3rd component of `.cfg` file.
+/// Format: "An,ch_id,ph,ccbm,uu,a,b,skew,min,max,primary,secondary,PS".
+fn serialize_comtrade_config_3(config: K, num_analog_channel: i32, file_type: &str, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  let length = num_analog_channel as usize;
+  let indices = get_config_list::<I>(config, "analog_channel_index", qtype::INT_LIST, length, "invalid analog channel index\0")?;
+  let ids = get_config_list::<S>(config, "analog_channel_id", qtype::SYMBOL_LIST, length, "invalid analog channel id\0")?;
+  let phases = get_config_list::<S>(config, "analog_channel_phase", qtype::SYMBOL_LIST, length, "invalid analog channel phase\0")?;
+  let components = get_config_list::<S>(config, "circuit_component_being_monitored", qtype::SYMBOL_LIST, length, "invalid circuit component being monitored\0")?;
+  let units = get_config_list::<S>(config, "channel_units", qtype::SYMBOL_LIST, length, "invalid channel units\0")?;
+  let multipliers = get_config_list::<E>(config, "channel_multiplier", qtype::REAL_LIST, length, "invalid channel multiplier\0")?;
+  let offsets = get_config_list::<E>(config, "channel_offset_adder", qtype::REAL_LIST, length, "invalid channel offset adder\0")?;
+  let skews = get_config_list::<E>(config, "skew", qtype::REAL_LIST, length, "invalid channel skew\0")?;
+  let minimums = get_config_list::<I>(config, "minimum_value", qtype::INT_LIST, length, "invalid minimum value\0")?;
+  let maximums = get_config_list::<I>(config, "maximum_value", qtype::INT_LIST, length, "invalid maximum value\0")?;
+  let primary_factors = get_config_list::<E>(config, "primary_factor", qtype::REAL_LIST, length, "invalid primary factor\0")?;
+  let secondary_factors = get_config_list::<E>(config, "secondary_factor", qtype::REAL_LIST, length, "invalid secondary factor\0")?;
+  let scaling_identifiers = get_config_list::<G>(config, "scaling_identifier", qtype::STRING, length, "invalid data scaling identifier\0")?;
+  // Data of ASCII and BINARY is limited to 5 digits.
+  let range = if file_type == "ascii" || file_type == "binary" {-99999..=99999} else {(qnull_base::I + 1)..=i32::MAX};
+  for (i, index) in indices.iter().enumerate(){
+    if *index < 1{
+      return Err("invalid analog channel index\0");
+    }
+    if !range.contains(&minimums[i]) || !range.contains(&maximums[i]) || minimums[i] > maximums[i]{
+      return Err("invalid minimum or maximum value\0");
+    }
+    if primary_factors[i].is_nan() || primary_factors[i] <= 0.0 || secondary_factors[i].is_nan() || secondary_factors[i] <= 0.0{
+      return Err("invalid primary or secondary factor\0");
+    }
+    let scaling_identifier = match scaling_identifiers[i]{
+      b'p' | b'P' => 'P',
+      b's' | b'S' => 'S',
+      _ => return Err("invalid data scaling identifier\0")
+    };
+    lines.push(format!("{},{},{},{},{},{},{},{},{},{},{},{},{}",
+      index,
+      serialize_comtrade_config_text(S_to_str(ids[i]), 64, "invalid analog channel id\0")?,
+      serialize_comtrade_config_text(S_to_str(phases[i]), 2, "invalid analog channel phase\0")?,
+      serialize_comtrade_config_text(S_to_str(components[i]), 64, "invalid circuit component being monitored\0")?,
+      serialize_comtrade_config_text(S_to_str(units[i]), 32, "invalid channel units\0")?,
+      serialize_comtrade_config_real(multipliers[i], "invalid channel multiplier\0")?,
+      serialize_comtrade_config_real(offsets[i], "invalid channel offset adder\0")?,
+      serialize_comtrade_config_real(skews[i], "invalid channel skew\0")?,
+      minimums[i],
+      maximums[i],
+      serialize_comtrade_config_real(primary_factors[i], "invalid primary factor\0")?,
+      serialize_comtrade_config_real(secondary_factors[i], "invalid secondary factor\0")?,
+      scaling_identifier
+    ));
+  }
+  Ok(())
+}
+
+/// Serialize 4th component of `.cfg` file.
+/// Format: "Dn,ch_id,ph,ccbm,y".
+fn serialize_comtrade_config_4(config: K, num_status_channel: i32, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  let length = num_status_channel as usize;
+  let indices = get_config_list::<I>(config, "status_channel_index", qtype::INT_LIST, length, "invalid status channel index\0")?;
+  let ids = get_config_list::<S>(config, "status_channel_id", qtype::SYMBOL_LIST, length, "invalid status channel id\0")?;
+  let phases = get_config_list::<S>(config, "status_channel_phase", qtype::SYMBOL_LIST, length, "invalid status channel phase\0")?;
+  // Circuit components of status channels follow those of analog channels.
+  let components = check_config_list::<S>(get_last_config_value(config, "circuit_component_being_monitored")?, qtype::SYMBOL_LIST, length, "invalid circuit component being monitored\0")?;
+  let states = get_config_list::<G>(config, "channel_state", qtype::BOOL_LIST, length, "invalid channel state\0")?;
+  for (i, index) in indices.iter().enumerate(){
+    if *index < 1{
+      return Err("invalid status channel index\0");
+    }
+    lines.push(format!("{},{},{},{},{}",
+      index,
+      serialize_comtrade_config_text(S_to_str(ids[i]), 64, "invalid status channel id\0")?,
+      serialize_comtrade_config_text(S_to_str(phases[i]), 2, "invalid status channel phase\0")?,
+      serialize_comtrade_config_text(S_to_str(components[i]), 64, "invalid circuit component being monitored\0")?,
+      states[i]
+    ));
+  }
+  Ok(())
+}
+
+/// Serialize 5th component (line) of `.cfg` file.
+/// Format: "lf". Null is written as an empty field.
+fn serialize_comtrade_config_5(config: K, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  let line_frequency = get_dictionary_value(config, "line_frequency")?.get_real()?;
+  if line_frequency.is_nan(){
+    lines.push(String::new());
+  }
+  else{
+    lines.push(serialize_comtrade_config_real(line_frequency, "invalid line frequency\0")?);
+  }
+  Ok(())
+}
+
+/// Serialize 6th component of `.cfg` file.
+/// "nrates"
+/// "samp,endsamp"
+/// ...
+/// "samp,endsamp"
+fn serialize_comtrade_config_6(config: K, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  let number_of_sample_rates = get_dictionary_value(config, "number_of_sample_rates")?.get_int()?;
+  if number_of_sample_rates < 0{
+    return Err("invalid number of sample rates\0");
+  }
+  // Line of sample rate exits even if nrates is 0.
+  let length = std::cmp::max(number_of_sample_rates, 1) as usize;
+  let sample_rates = get_config_list::<E>(config, "sample_rates", qtype::REAL_LIST, length, "invalid sample rate\0")?;
+  let last_sample_numbers = get_config_list::<I>(config, "last_sample_number", qtype::INT_LIST, length, "invalid last sample number\0")?;
+  lines.push(number_of_sample_rates.to_string());
+  for (sample_rate, last_sample_number) in sample_rates.iter().zip(last_sample_numbers.iter()){
+    if *sample_rate < 0.0 || *last_sample_number == qnull_base::I || *last_sample_number < 0{
+      return Err("invalid sample rate\0");
+    }
+    lines.push(format!("{},{}", serialize_comtrade_config_real(*sample_rate, "invalid sample rate\0")?, last_sample_number));
+  }
+  Ok(())
+}
+
+/// Serialize each line of 7th component of `.cfg` file. Null is written as an empty line.
+/// "dd/mm/yyyy,hh:mm:ss.ssssss"
+fn serialize_comtrade_config_7_inner(timestamp: i64) -> String{
+  if timestamp == qnull_base::J{
+    String::new()
   }
   else{
-    new_dictionary(keys, values)
-  }   
+    Utc.timestamp_nanos(timestamp + KDB_TIMESTAMP_OFFSET).format("%d/%m/%Y,%H:%M:%S%.6f").to_string()
+  }
+}
+
+/// Serialize 7th component of `.cfg` file.
+/// "dd/mm/yyyy,hh:mm:ss.ssssss"
+/// "dd/mm/yyyy,hh:mm:ss.ssssss"
+fn serialize_comtrade_config_7(config: K, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  lines.push(serialize_comtrade_config_7_inner(get_dictionary_value(config, "first_data_time")?.get_long()?));
+  lines.push(serialize_comtrade_config_7_inner(get_dictionary_value(config, "event_time")?.get_long()?));
+  Ok(())
+}
+
+/// Serialize 8th component (line) of `.cfg` file.
+/// Format: "ft". `BINARY32` and `FLOAT32` exist only in the 2013 revision.
+fn serialize_comtrade_config_8(file_type: &str, revision_year: i32, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  match file_type{
+    "ascii" | "binary" => (),
+    "binary32" | "float32" if revision_year >= 2013 => (),
+    "binary32" | "float32" => return Err("file type is not supported in the revision year\0"),
+    _ => return Err("invalid file type\0")
+  }
+  lines.push(file_type.to_uppercase());
+  Ok(())
+}
+
+/// Serialize 9th component (line) of `.cfg` file.
+/// Format: "timemult".
+fn serialize_comtrade_config_9(config: K, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  let timestamp_multiplication_factor = get_dictionary_value(config, "timestamp_multiplication_factor")?.get_real()?;
+  if timestamp_multiplication_factor.is_nan() || timestamp_multiplication_factor <= 0.0{
+    return Err("invalid timestamp multiplication factor\0");
+  }
+  lines.push(serialize_comtrade_config_real(timestamp_multiplication_factor, "invalid timestamp multiplication factor\0")?);
+  Ok(())
+}
+
+/// Serialize a time zone offset in nanoseconds into a field of the 10th component of `.cfg` file.
+/// Format: "[-]hh[hmm]" (e.g. "-5", "10h30"). Null is written as "x".
+fn serialize_comtrade_config_10_inner(offset: i64, error: &'static str) -> Result<String, &'static str>{
+  if offset == qnull_base::J{
+    return Ok(String::from("x"));
+  }
+  let sign = if offset < 0 {"-"} else {""};
+  let minutes = offset.abs() / 60_000_000_000;
+  if offset.abs() % 60_000_000_000 != 0 || minutes > 24 * 60{
+    return Err(error);
+  }
+  if minutes % 60 == 0{
+    Ok(format!("{}{}", sign, minutes / 60))
+  }
+  else{
+    Ok(format!("{}{}h{:02}", sign, minutes / 60, minutes % 60))
+  }
+}
+
+/// Serialize 10th component (line) of `.cfg` file. This component exists only in the 2013 revision.
+/// Format: "time_code,local_code".
+fn serialize_comtrade_config_10(config: K, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  let time_code = serialize_comtrade_config_10_inner(get_dictionary_value(config, "time_code")?.get_long()?, "invalid time code\0")?;
+  let local_code = serialize_comtrade_config_10_inner(get_dictionary_value(config, "local_code")?.get_long()?, "invalid local code\0")?;
+  lines.push(format!("{},{}", time_code, local_code));
+  Ok(())
+}
+
+/// Serialize 11th component (line) of `.cfg` file. This component exists only in the 2013 revision.
+/// Format: "tmq_code,leapsec".
+fn serialize_comtrade_config_11(config: K, lines: &mut Vec<String>) -> Result<(), &'static str>{
+  let time_quality_code = get_dictionary_value(config, "time_quality_code")?.get_int()?;
+  let leap_second = get_dictionary_value(config, "leap_second")?.get_int()?;
+  if !(0..16).contains(&time_quality_code){
+    return Err("invalid time quality code\0");
+  }
+  if !(0..4).contains(&leap_second){
+    return Err("invalid leap second indicator\0");
+  }
+  lines.push(format!("{:X},{}", time_quality_code, leap_second));
+  Ok(())
+}
+
+/// Serialize a configuration dictionary in the form of `deserialize_comtrade_config` into contents of the configuration
+///  file (`.cfg`) of COMTRADE format. Lines are delimited by <CR/LF>.
+pub(crate) fn serialize_comtrade_config_contents(config: K) -> Result<String, &'static str>{
+  let mut lines = Vec::new();
+  let revision_year = serialize_comtrade_config_1(config, &mut lines)?;
+  let (num_analog_channel, num_status_channel) = serialize_comtrade_config_2(config, &mut lines)?;
+  // File type is needed to validate minimum and maximum values of analog channels.
+  let file_type = get_dictionary_value(config, "file_type")?.get_symbol()?.to_lowercase();
+  serialize_comtrade_config_3(config, num_analog_channel, file_type.as_str(), &mut lines)?;
+  serialize_comtrade_config_4(config, num_status_channel, &mut lines)?;
+  serialize_comtrade_config_5(config, &mut lines)?;
+  serialize_comtrade_config_6(config, &mut lines)?;
+  serialize_comtrade_config_7(config, &mut lines)?;
+  serialize_comtrade_config_8(file_type.as_str(), revision_year, &mut lines)?;
+  serialize_comtrade_config_9(config, &mut lines)?;
+  if revision_year >= 2013{
+    serialize_comtrade_config_10(config, &mut lines)?;
+    serialize_comtrade_config_11(config, &mut lines)?;
+  }
+  lines.push(String::new());
+  Ok(lines.join("\r\n"))
+}
+
+/// Raise the revision year of a configuration filling fields which do not exist in the original revision. Primary and
+///  secondary factors missing in the 1991 revision are filled with 1 and the data scaling identifier with 'p'. Time codes
+///  missing before the 2013 revision are filled as null and the time quality code and the leap second indicator with 0.
+///  Returns a new configuration.
+pub(crate) fn upgrade_comtrade_config_revision(config: K, revision_year: i32) -> Result<K, &'static str>{
+  let current_revision_year = get_dictionary_value(config, "revision_year")?.get_int()?;
+  let mut updates = Vec::new();
+  if current_revision_year >= revision_year{
+    return update_dictionary(config, updates);
+  }
+  updates.push(("revision_year", new_int(revision_year)));
+  if current_revision_year == 1991{
+    // Check all lists before building new values.
+    let length = get_dictionary_value(config, "number_of_analog_channels")?.get_int()? as usize;
+    let primary_factors = get_config_list::<E>(config, "primary_factor", qtype::REAL_LIST, length, "invalid primary factor\0")?;
+    let secondary_factors = get_config_list::<E>(config, "secondary_factor", qtype::REAL_LIST, length, "invalid secondary factor\0")?;
+    let scaling_identifiers = get_config_list::<G>(config, "scaling_identifier", qtype::STRING, length, "invalid data scaling identifier\0")?;
+    for (key, factors) in [("primary_factor", primary_factors), ("secondary_factor", secondary_factors)].iter(){
+      let filled = new_list(qtype::REAL_LIST, factors.len() as J);
+      filled.as_mut_slice::<E>().iter_mut().zip(factors.iter()).for_each(|(filled, factor)| *filled = if factor.is_nan() {1.0} else {*factor});
+      updates.push((key, filled));
+    }
+    let filled = new_list(qtype::STRING, scaling_identifiers.len() as J);
+    filled.as_mut_slice::<G>().iter_mut().zip(scaling_identifiers.iter()).for_each(|(filled, identifier)| *filled = if *identifier == b' ' {b'p'} else {*identifier});
+    updates.push(("scaling_identifier", filled));
+  }
+  if revision_year >= 2013{
+    if find_dictionary_value(config, "time_code")?.is_none(){
+      updates.push(("time_code", new_timespan(qnull_base::J)));
+      updates.push(("local_code", new_timespan(qnull_base::J)));
+    }
+    if find_dictionary_value(config, "time_quality_code")?.is_none(){
+      updates.push(("time_quality_code", new_int(0)));
+      updates.push(("leap_second", new_int(0)));
+    }
+  }
+  update_dictionary(config, updates)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Interface                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Deserialize the configuration file (`.cfg`) of COMTRADE format into q dictionary.
+/// # Parameters
+/// - `data`: 
+///   - symbol: File path which starts with `:`. Lines can be delimited by <CR/LF>, <LF> or <CR>.
+///   - string: File contents.
+#[no_mangle]
+pub extern "C" fn deserialize_comtrade_config(data: K) -> K{
+  catch_panic(||{
+    let string;
+    let mut contents_buffer = ContentsBuffer::default();
+    // Load data into string.
+    set_string!(data, string, contents_buffer);
+
+    match deserialize_comtrade_config_contents(string){
+      Ok(config) => config,
+      Err(error) => error.in_source(data).to_q_error()
+    }
+  })
+}
+
+/// Serialize a configuration into the configuration file (`.cfg`) of COMTRADE format. Channel counts and field formats
+///  are validated before writing.
+/// # Parameters
+/// - `config`: Dictionary in the form of `deserialize_comtrade_config`. Revision year must be 1999 or 2013.
+/// - `path`: File path which starts with `:`.
+#[no_mangle]
+pub extern "C" fn serialize_comtrade_config(config: K, path: K) -> K{
+  catch_panic(||{
+    match serialize_comtrade_config_contents(config).and_then(|contents| write_data(path, contents.as_bytes())){
+      Ok(_) => increment_reference_count(path),
+      Err(error) => new_error(error)
+    }
+  })
 }
@@ -0,0 +1,188 @@
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::path::Path;
+use kdbplus::api::*;
+use super::{ContentsBuffer, map_file, write_file, catch_panic, ComtradeError};
+use super::cff::{SECTION_PREFIX, split_comtrade_cff, section_to_str};
+use super::config::{deserialize_comtrade_config_contents, serialize_comtrade_config_contents, upgrade_comtrade_config_revision};
+use super::data::{DataSpec, deserialize_comtrade_data_contents, serialize_comtrade_data_contents, convert_comtrade_data};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Struct                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Files of a recording mapped into memory. Information and header are carried over as they are.
+struct Recording<'a>{
+  config: &'a [u8],
+  data: &'a [u8],
+  info: Option<&'a [u8]>,
+  header: Option<&'a [u8]>
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Private Fucntions                           //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Map a file of a given path into memory. `None` is returned if the file does not exist.
+fn read_file<'a>(path: &str, contents_buffer: &'a mut ContentsBuffer) -> Result<Option<&'a [u8]>, &'static str>{
+  if Path::new(path).is_file(){
+    map_file(path, contents_buffer).map(Some)
+  }
+  else{
+    Ok(None)
+  }
+}
+
+/// Check if a path is of the combined file (`.cff`).
+fn is_cff(path: &str) -> bool{
+  path.to_ascii_lowercase().ends_with(".cff")
+}
+
+/// Load a recording from the combined file (`.cff`) or files sharing a path without extension, i.e., `<path>.cfg`,
+///  `<path>.dat` and optionally `<path>.inf` and `<path>.hdr`. Files are mapped into memory held by `contents_buffers`.
+fn load_recording<'a>(path: &str, contents_buffers: &'a mut [ContentsBuffer; 4]) -> Result<Recording<'a>, &'static str>{
+  let [config_buffer, data_buffer, info_buffer, header_buffer] = contents_buffers;
+  if is_cff(path){
+    let bytes = match read_file(path, config_buffer)?{
+      Some(bytes) => bytes,
+      None => return Err("no such file\0")
+    };
+    let sections = split_comtrade_cff(bytes)?;
+    Ok(Recording{
+      config: sections.config.ok_or("missing CFG section\0")?,
+      data: sections.data.ok_or("missing DAT section\0")?,
+      info: sections.info,
+      header: sections.header
+    })
+  }
+  else{
+    Ok(Recording{
+      config: read_file(&format!("{}.cfg", path), config_buffer)?.ok_or("no such file - .cfg\0")?,
+      data: read_file(&format!("{}.dat", path), data_buffer)?.ok_or("no such file - .dat\0")?,
+      info: read_file(&format!("{}.inf", path), info_buffer)?,
+      header: read_file(&format!("{}.hdr", path), header_buffer)?
+    })
+  }
+}
+
+/// Append a section of `.cff` file. Text sections are terminated by <CR/LF>.
+/// Separator format: "--- file type: CFG ---", "--- file type: DAT BINARY: 1234 ---".
+fn push_cff_section(bytes: &mut Vec<u8>, file_type: &str, contents: &[u8], with_byte_count: bool){
+  bytes.extend_from_slice(SECTION_PREFIX);
+  if with_byte_count{
+    bytes.extend_from_slice(format!(" {}: {} ---\r\n", file_type, contents.len()).as_bytes());
+  }
+  else{
+    bytes.extend_from_slice(format!(" {} ---\r\n", file_type).as_bytes());
+  }
+  bytes.extend_from_slice(contents);
+  if !with_byte_count && !contents.is_empty() && !contents.ends_with(b"\n"){
+    bytes.extend_from_slice(b"\r\n");
+  }
+}
+
+/// Convert a recording into a given data file type. Returns contents of the new configuration and data files.
+///  The revision year is raised to 2013 for `binary32`, `float32` and the combined file and to 1999 for a
+///  configuration of the 1991 revision.
+fn convert_recording(recording: &Recording, file_type: &str, cff: bool) -> Result<(String, Vec<u8>), ComtradeError>{
+  let config = deserialize_comtrade_config_contents(section_to_str(recording.config)?)?;
+  let table = match DataSpec::from_config(config).map_err(ComtradeError::from).and_then(|spec| deserialize_comtrade_data_contents(recording.data, &spec)){
+    Ok(table) => table,
+    Err(error) => {
+      decrement_reference_count(config);
+      return Err(error);
+    }
+  };
+  let revision_year = if cff || file_type == "binary32" || file_type == "float32" {2013} else {1999};
+  let converted_config = convert_comtrade_data(table, config, file_type).and_then(|converted_config|{
+    let upgraded_config = upgrade_comtrade_config_revision(converted_config, revision_year);
+    decrement_reference_count(converted_config);
+    upgraded_config
+  });
+  decrement_reference_count(config);
+  let converted_config = match converted_config{
+    Ok(converted_config) => converted_config,
+    Err(error) => {
+      decrement_reference_count(table);
+      return Err(error.into());
+    }
+  };
+  let result = serialize_comtrade_config_contents(converted_config).and_then(|config_contents|{
+    let data_contents = DataSpec::from_config(converted_config).and_then(|spec| serialize_comtrade_data_contents(table, &spec))?;
+    Ok((config_contents, data_contents))
+  });
+  decrement_reference_count(converted_config);
+  decrement_reference_count(table);
+  result.map_err(ComtradeError::from)
+}
+
+/// Convert a recording into a given data file type and write it into the combined file (`.cff`) or files sharing a path
+///  without extension.
+///  Errors in the source recording are located by the source path.
+fn convert_comtrade_files(source: &str, destination: &str, file_type: &str) -> Result<(), ComtradeError>{
+  let cff = is_cff(destination);
+  let (config, data, info, header) = {
+    let mut contents_buffers = Default::default();
+    let recording = load_recording(source, &mut contents_buffers)?;
+    let (config, data) = convert_recording(&recording, file_type, cff).map_err(|error| error.in_file(source))?;
+    // Copy information and header and unmap the source before writing as the destination may be the source.
+    (config, data, recording.info.map(<[u8]>::to_vec), recording.header.map(<[u8]>::to_vec))
+  };
+  if cff{
+    let mut bytes = Vec::new();
+    push_cff_section(&mut bytes, "CFG", config.as_bytes(), false);
+    if let Some(info) = &info{
+      push_cff_section(&mut bytes, "INF", info, false);
+    }
+    if let Some(header) = &header{
+      push_cff_section(&mut bytes, "HDR", header, false);
+    }
+    push_cff_section(&mut bytes, format!("DAT {}", file_type.to_ascii_uppercase()).as_str(), &data, true);
+    write_file(destination, &bytes).map_err(ComtradeError::from)
+  }
+  else{
+    write_file(&format!("{}.cfg", destination), config.as_bytes())?;
+    write_file(&format!("{}.dat", destination), &data)?;
+    if let Some(info) = &info{
+      write_file(&format!("{}.inf", destination), info)?;
+    }
+    if let Some(header) = &header{
+      write_file(&format!("{}.hdr", destination), header)?;
+    }
+    Ok(())
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Interface                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Convert a recording of COMTRADE format into another data file type or the combined file (`.cff`). Analog data are kept
+///  as they are if they fit in the range of the new file type. Otherwise they are re-scaled with new factors.
+/// # Parameters
+/// - `source`: File path which starts with `:`. Path ending with `.cff` is loaded as the combined file. Otherwise files
+///   `<source>.cfg`, `<source>.dat` and optionally `<source>.inf` and `<source>.hdr` are loaded.
+/// - `destination`: File path which starts with `:`. Path ending with `.cff` is written as the combined file. Otherwise
+///   files `<destination>.cfg`, `<destination>.dat` and `<destination>.inf` and `<destination>.hdr` if they exist in the source
+///   are written.
+/// - `file_type`: Data file type of the destination: `ascii`, `binary`, `binary32` or `float32`.
+#[no_mangle]
+pub extern "C" fn convert_comtrade(source: K, destination: K, file_type: K) -> K{
+  catch_panic(||{
+    let result = match (source.get_symbol(), destination.get_symbol(), file_type.get_symbol()){
+      (Ok(source_), Ok(destination_), Ok(file_type_)) => {
+        match (source_.strip_prefix(':'), destination_.strip_prefix(':')){
+          (Some(source_), Some(destination_)) => convert_comtrade_files(source_, destination_, file_type_),
+          _ => Err("invalid file name - missing ':'\0".into())
+        }
+      },
+      _ => Err("expected symbol\0".into())
+    };
+    match result{
+      Ok(_) => increment_reference_count(destination),
+      Err(error) => error.to_q_error()
+    }
+  })
+}
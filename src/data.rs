@@ -2,284 +2,1598 @@
 //                            Load Libraries                            //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::convert::TryInto;
 use bitvec::prelude::*;
 use kdbplus::*;
 use kdbplus::api::*;
-use super::{parse_token, load_ascii_data, load_binary_data};
+use super::{parse_token, ContentsBuffer, load_ascii_data, load_binary_data, write_data, find_dictionary_value, get_dictionary_value, check_config_list, get_config_list, get_bool_option, get_long_option, get_symbol_option, update_dictionary, split_lines, Lines, catch_panic, ComtradeError};
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
-//                          Private Fucntions                           //
+//                          Global Variables                            //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
-/// Deserialize each line of data (.dat) file written in ASCII format.
-/// "n, timestamp, A1, A2,···Ak, D1, D2,···Dm"
-fn deserialize_comtrade_data_inner_ascii(line: &str, values: K, num_analog_channel: i32, num_status_channel: i32, critical_timestamp: bool, first_data_time: i64, timestamp_multiplication_factor: f64) -> Result<(), &'static str>{
-  let tokens=line.split(',').collect::<Vec<&str>>();
-  if tokens.len() != num_analog_channel as usize + num_status_channel  as usize + 2{
-    Err("the number of fields is fewer than expected\0")
+/// q keywords which cannot be used as a column name.
+const Q_RESERVED_WORDS: [&str; 47] = [
+  "abs", "acos", "asin", "atan", "avg", "bin", "binr", "by", "cor", "cos", "cov", "delete", "dev", "div", "do", "enlist",
+  "exec", "exit", "exp", "from", "getenv", "hopen", "if", "in", "insert", "last", "like", "log", "max", "min", "prd",
+  "select", "setenv", "sin", "sqrt", "ss", "sum", "tan", "update", "var", "wavg", "while", "within", "wsum", "xexp",
+  "and", "or"
+];
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                                Enum                                  //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Analog data stored in data file (`.dat`).
+#[derive(Clone, Copy)]
+enum AnalogData{
+  /// Integer data. Null is represented by q null int.
+  Int(i32),
+  /// Floating point data. Null is represented by NaN.
+  Real(f32)
+}
+
+/// Format of data file (`.dat`) specified by the file type in the 8th component of `.cfg` file.
+#[derive(Clone, Copy, PartialEq)]
+enum DataFileType{
+  Ascii,
+  /// Analog data is stored in 2 bytes.
+  Binary,
+  /// Analog data is stored in 4 bytes.
+  Binary32,
+  /// Analog data is stored as IEEE 754 single-precision floating point.
+  Float32
+}
+
+impl DataFileType{
+  /// Build file type from a file type symbol of deserialized config.
+  fn from_symbol(file_type: &str) -> Result<Self, &'static str>{
+    match file_type{
+      "ascii" => Ok(DataFileType::Ascii),
+      "binary" => Ok(DataFileType::Binary),
+      "binary32" => Ok(DataFileType::Binary32),
+      "float32" => Ok(DataFileType::Float32),
+      _ => Err("invalid file type\0")
+    }
+  }
+
+  /// Size of an analog data in bytes in a binary record.
+  fn analog_size(&self) -> usize{
+    match self{
+      DataFileType::Binary32 | DataFileType::Float32 => 4,
+      _ => 2
+    }
+  }
+
+  /// Range of integer analog data excluding the null sentinel. Data of ASCII is limited to 5 digits.
+  fn analog_range(&self) -> (i32, i32){
+    match self{
+      DataFileType::Ascii => (-99999, 99998),
+      DataFileType::Binary => (-32767, 32767),
+      _ => (-i32::MAX, i32::MAX)
+    }
+  }
+
+  /// q type of analog data columns without conversion into engineering unit.
+  fn analog_qtype(&self) -> i8{
+    match self{
+      DataFileType::Float32 => qtype::REAL_LIST,
+      _ => qtype::INT_LIST
+    }
+  }
+}
+
+/// Correction of sampling skew of analog channels. Skews are held in nanoseconds.
+enum SkewCorrection{
+  /// Skew is ignored.
+  None,
+  /// Add a time column corrected by the skew for each analog channel.
+  TimeColumn(Vec<i64>),
+  /// Resample analog data onto the common sample instants by linear interpolation.
+  Interpolate(Vec<i64>)
+}
+
+/// Range of records to load from data file (`.dat`). Bounds are inclusive.
+enum RecordRange{
+  /// All records are loaded.
+  All,
+  /// Records whose sample number is within the range.
+  Sample(i64, i64),
+  /// Records whose time is within the range (nanoseconds since kdb+ epoch).
+  Time(i64, i64)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Struct                                 //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Layout of data file (`.dat`) described by the configuration file.
+pub(crate) struct DataSpec{
+  num_analog_channel: i32,
+  num_status_channel: i32,
+  critical_timestamp: bool,
+  first_data_time: i64,
+  timestamp_multiplication_factor: f64,
+  /// Pairs of sample rate (Hz) and the last sample number at the rate. Used to derive time of a record whose timestamp
+  ///  is blank.
+  sample_rates: Vec<(f64, i64)>,
+  file_type: DataFileType,
+  /// Pairs of multiplier and offset of each analog channel to convert data into engineering unit.
+  analog_conversion: Option<Vec<(f64, f64)>>,
+  /// Column names of analog channels followed by status channels. `analog_channel_N` and `status_channel_N` are used
+  ///  if not given.
+  column_names: Option<Vec<String>>,
+  /// Correction of sampling skew of analog channels.
+  skew_correction: SkewCorrection,
+  /// Indices of analog channels to load in the order of columns.
+  analog_channels: Vec<usize>,
+  /// Indices of status channels to load in the order of columns.
+  status_channels: Vec<usize>,
+  /// Range of records to load.
+  record_range: RecordRange,
+  /// The number of threads to decode data file written in ASCII format.
+  threads: usize,
+  /// The number of records expected from the last `endsamp` of the configuration. Used to reserve buffers.
+  expected_records: Option<usize>
+}
+
+/// Records of data file (`.dat`) written in ASCII format decoded into native buffers. q lists are built from them on
+///  the main thread as kdb+ API is not thread-safe.
+struct AsciiRecords{
+  sample_numbers: Vec<i32>,
+  times: Vec<i64>,
+  /// Analog data of selected channels. Null is represented by q null int.
+  analog: Vec<Vec<i32>>,
+  /// Status data of selected channels.
+  status: Vec<Vec<bool>>,
+  /// Whether a record following the record range was reached.
+  reached_end: bool,
+  /// Error at the first invalid record.
+  error: Option<ComtradeError>
+}
+
+impl AsciiRecords{
+  /// Empty buffers for the selected channels reserving `capacity` records.
+  fn new(spec: &DataSpec, capacity: usize) -> Self{
+    AsciiRecords{
+      sample_numbers: Vec::with_capacity(capacity),
+      times: Vec::with_capacity(capacity),
+      analog: vec![Vec::with_capacity(capacity); spec.analog_channels.len()],
+      status: vec![Vec::with_capacity(capacity); spec.status_channels.len()],
+      reached_end: false,
+      error: None
+    }
+  }
+
+  /// The number of decoded records.
+  fn len(&self) -> usize{
+    self.sample_numbers.len()
+  }
+
+  /// Write decoded records to columns prepared by `new_data_columns` from the `row`th element.
+  fn write_to(&self, values: K, row: usize, spec: &DataSpec){
+    let values_slice = values.as_mut_slice::<K>();
+    let rows = row..row+self.len();
+    values_slice[0].as_mut_slice::<I>()[rows.clone()].copy_from_slice(&self.sample_numbers);
+    values_slice[1].as_mut_slice::<J>()[rows.clone()].copy_from_slice(&self.times);
+    for (column, (data, channel)) in self.analog.iter().zip(spec.analog_channels.iter()).enumerate(){
+      if spec.analog_conversion.is_none(){
+        values_slice[2+column].as_mut_slice::<I>()[rows.clone()].copy_from_slice(data);
+      }
+      else{
+        for (offset, num) in data.iter().enumerate(){
+          set_analog(values_slice[2+column], row+offset, AnalogData::Int(*num), *channel, spec);
+        }
+      }
+    }
+    let column_offset = 2 + self.analog.len();
+    for (column, data) in self.status.iter().enumerate(){
+      values_slice[column_offset+column].as_mut_slice::<G>()[rows.clone()].iter_mut().zip(data.iter()).for_each(|(value, flag)| *value = *flag as G);
+    }
+  }
+}
+
+impl DataSpec{
+  /// Build data layout from a configuration deserialized by `deserialize_comtrade_config`.
+  pub(crate) fn from_config(config: K) -> Result<Self, &'static str>{
+    let number_of_sample_rates = get_dictionary_value(config, "number_of_sample_rates")?.get_int()?;
+    let sample_rates = get_dictionary_value(config, "sample_rates")?;
+    let sample_rates = check_config_list::<E>(sample_rates, qtype::REAL_LIST, sample_rates.len() as usize, "invalid sample rate\0")?;
+    let last_sample_numbers = get_dictionary_value(config, "last_sample_number")?;
+    let last_sample_numbers = check_config_list::<I>(last_sample_numbers, qtype::INT_LIST, last_sample_numbers.len() as usize, "invalid last sample number\0")?;
+    if sample_rates.len() != last_sample_numbers.len(){
+      return Err("the number of sample rates does not match the number of last sample numbers\0");
+    }
+    let num_analog_channel = get_dictionary_value(config, "number_of_analog_channels")?.get_int()?;
+    let num_status_channel = get_dictionary_value(config, "number_of_status_channels")?.get_int()?;
+    Ok(DataSpec{
+      num_analog_channel,
+      num_status_channel,
+      // Timestamp is critical only if neither the number of sample rates nor the sample rate is given.
+      critical_timestamp: number_of_sample_rates == 0 && sample_rates.first().copied().unwrap_or(0.0) == 0.0,
+      first_data_time: get_dictionary_value(config, "first_data_time")?.get_long()?,
+      timestamp_multiplication_factor: get_dictionary_value(config, "timestamp_multiplication_factor")?.get_real()? as f64,
+      sample_rates: sample_rates.iter().zip(last_sample_numbers.iter()).map(|(rate, last)| (*rate as f64, *last as i64)).collect(),
+      file_type: DataFileType::from_symbol(get_dictionary_value(config, "file_type")?.get_symbol()?)?,
+      analog_conversion: None,
+      column_names: None,
+      skew_correction: SkewCorrection::None,
+      analog_channels: (0..num_analog_channel.max(0) as usize).collect(),
+      status_channels: (0..num_status_channel.max(0) as usize).collect(),
+      record_range: RecordRange::All,
+      threads: 1,
+      expected_records: last_sample_numbers.last().filter(|last| **last > 0).map(|last| *last as usize)
+    })
   }
-  else{
-    let values_slice=values.as_mut_slice::<K>();
 
-    // Deserialize sample number
-    parse_token![i32; tokens, 0, values_slice, 0, "invalid sample number\0"];
+  /// Decode data file written in ASCII format with `threads` worker threads. Lines are split into as many blocks.
+  ///  The number of threads is capped by the available parallelism of the machine.
+  pub(crate) fn set_threads(&mut self, threads: i64) -> Result<(), &'static str>{
+    if threads < 1{
+      return Err("invalid threads - expected positive int or long\0");
+    }
+    let max_threads = std::thread::available_parallelism().map(|parallelism| parallelism.get()).unwrap_or(1);
+    self.threads = (threads as u64).min(max_threads as u64) as usize;
+    Ok(())
+  }
 
-    // Deserialize timestamp
-    match tokens[1].parse::<i64>(){
-      Ok(micros) => {
-        values_slice[1].push_raw((timestamp_multiplication_factor * (1000 * micros) as f64) as i64 + first_data_time).unwrap();
+  /// Load only records within a range of sample numbers or time. Bounds are inclusive and a null bound leaves the
+  ///  range open on the side.
+  /// # Parameters
+  /// - `sample_range`: Pair of the first and the last sample numbers (int or long).
+  /// - `time_range`: Pair of the first and the last time (timestamp).
+  pub(crate) fn set_record_range(&mut self, sample_range: Option<K>, time_range: Option<K>) -> Result<(), &'static str>{
+    self.record_range = match (sample_range, time_range){
+      (None, None) => RecordRange::All,
+      (Some(_), Some(_)) => return Err("sample range and time range cannot be given together\0"),
+      (Some(range), None) => {
+        let bounds = match range.get_type(){
+          qtype::INT_LIST => range.as_mut_slice::<I>().iter().map(|bound| if *bound == qnull_base::I {qnull_base::J} else {*bound as J}).collect::<Vec<J>>(),
+          qtype::LONG_LIST => range.as_mut_slice::<J>().to_vec(),
+          _ => return Err("invalid sample range - expected pair of int or long\0")
+        };
+        match bounds[..]{
+          [first, last] => RecordRange::Sample(first, if last == qnull_base::J {J::MAX} else {last}),
+          _ => return Err("invalid sample range - expected pair of int or long\0")
+        }
       },
-      Err(_) => {
-        if critical_timestamp{
-          return Err("invalid timestamp\0")
+      (None, Some(range)) => {
+        if range.get_type() != qtype::TIMESTAMP_LIST{
+          return Err("invalid time range - expected pair of timestamp\0");
         }
-        else{
-          values_slice[1].push_raw(qnull_base::J).unwrap();
+        match range.as_mut_slice::<J>(){
+          [first, last] => RecordRange::Time(*first, if *last == qnull_base::J {J::MAX} else {*last}),
+          _ => return Err("invalid time range - expected pair of timestamp\0")
         }
       }
+    };
+    Ok(())
+  }
+
+  /// Build data layout from arguments of `deserialize_comtrade_data`. Sample rates are not given and time of a record
+  ///  whose timestamp is blank is filled as null.
+  fn from_arguments(num_analog_channel: K, num_status_channel: K, critical_timestamp: K, first_data_time: K, timestamp_multiplication_factor: K, file_type: K) -> Result<Self, &'static str>{
+    let num_analog_channel = num_analog_channel.get_int().map_err(|_| "invalid number of analog channels - expected int\0")?;
+    let num_status_channel = num_status_channel.get_int().map_err(|_| "invalid number of status channels - expected int\0")?;
+    if num_analog_channel < 0 || num_status_channel < 0{
+      return Err("invalid number of channels - expected non-negative int\0");
     }
+    Ok(DataSpec{
+      num_analog_channel,
+      num_status_channel,
+      critical_timestamp: critical_timestamp.get_bool().map_err(|_| "invalid critical timestamp flag - expected bool\0")?,
+      first_data_time: first_data_time.get_long().map_err(|_| "invalid first data time - expected timestamp\0")?,
+      timestamp_multiplication_factor: timestamp_multiplication_factor.get_real().map_err(|_| "invalid timestamp multiplication factor - expected real\0")? as f64,
+      sample_rates: Vec::new(),
+      file_type: match file_type.get_type(){
+        // Flag of whether data is encoded in ASCII or binary accepted by earlier versions.
+        qtype::BOOL_ATOM => if file_type.get_bool()? {DataFileType::Ascii} else {DataFileType::Binary},
+        _ => DataFileType::from_symbol(file_type.get_symbol().map_err(|_| "invalid file type - expected symbol or bool\0")?)?
+      },
+      analog_conversion: None,
+      column_names: None,
+      skew_correction: SkewCorrection::None,
+      analog_channels: (0..num_analog_channel as usize).collect(),
+      status_channels: (0..num_status_channel as usize).collect(),
+      record_range: RecordRange::All,
+      threads: 1,
+      // The number of records is not known.
+      expected_records: None
+    })
+  }
 
-    // Deserilize analog data
-    for i in 2..2+num_analog_channel as usize{
-      match tokens[i].parse::<i32>(){
-        Ok(num) if num == 99999 => {
-          values_slice[i].push_raw(qnull_base::I).unwrap();
-        },
-        Ok(num) => {
-          values_slice[i].push_raw(num).unwrap();
-        },
-        Err(_) => return Err("invalid analog channel data\0")
+  /// Load only the channels given by `analog` and `status` in the given order. Each selection is a list of channel
+  ///  indices (int or long) matched against `analog_channel_index`/`status_channel_index` of the configuration, or a
+  ///  list of channel IDs (symbol). `None` selects all channels.
+  pub(crate) fn set_channel_projection(&mut self, config: K, analog: Option<K>, status: Option<K>) -> Result<(), &'static str>{
+    if let Some(analog) = analog{
+      let indices = get_config_list::<I>(config, "analog_channel_index", qtype::INT_LIST, self.num_analog_channel as usize, "invalid analog channel index\0")?;
+      let ids = get_config_list::<S>(config, "analog_channel_id", qtype::SYMBOL_LIST, self.num_analog_channel as usize, "invalid analog channel id\0")?;
+      self.analog_channels = select_channels(analog, indices, ids, "unknown analog channel\0")?;
+    }
+    if let Some(status) = status{
+      let indices = get_config_list::<I>(config, "status_channel_index", qtype::INT_LIST, self.num_status_channel as usize, "invalid status channel index\0")?;
+      let ids = get_config_list::<S>(config, "status_channel_id", qtype::SYMBOL_LIST, self.num_status_channel as usize, "invalid status channel id\0")?;
+      self.status_channels = select_channels(status, indices, ids, "unknown status channel\0")?;
+    }
+    Ok(())
+  }
+
+  /// Correct sampling skew of analog channels with `skew` (in microseconds) of the configuration.
+  /// # Parameters
+  /// - `mode`:
+  ///   - `time_column`: Add a column `time_<analog column name>` holding the sample time corrected by the skew for each
+  ///     analog channel.
+  ///   - `interpolate`: Resample analog data onto the common sample instants by linear interpolation. Analog columns
+  ///     become float. Instants outside the sampled range are filled as null.
+  ///   - `none`: No correction. `skew` of the configuration is not read.
+  pub(crate) fn set_skew_correction(&mut self, config: K, mode: &str) -> Result<(), &'static str>{
+    let skews = ||{
+      let skews = get_config_list::<E>(config, "skew", qtype::REAL_LIST, self.num_analog_channel as usize, "invalid channel skew\0")?;
+      Ok(skews.iter().map(|skew| (*skew as f64 * 1000.0).round() as i64).collect::<Vec<i64>>())
+    };
+    self.skew_correction = match mode{
+      "none" => SkewCorrection::None,
+      "time_column" => SkewCorrection::TimeColumn(skews()?),
+      "interpolate" => {
+        let skews = skews()?;
+        if self.analog_conversion.is_none(){
+          // Deserialize analog data into float as it is.
+          self.analog_conversion = Some(vec![(1.0, 0.0); self.num_analog_channel as usize]);
+        }
+        SkewCorrection::Interpolate(skews)
+      },
+      _ => return Err("invalid skew correction - expected `none, `time_column or `interpolate\0")
+    };
+    Ok(())
+  }
+
+  /// Name columns after `analog_channel_id` and `status_channel_id` of the configuration. IDs are sanitised into
+  ///  valid q names and de-duplicated by appending `_2`, `_3`, ... An ID which has no valid character falls back to
+  ///  the index-based name.
+  pub(crate) fn set_channel_id_column_names(&mut self, config: K) -> Result<(), &'static str>{
+    let analog_ids = get_config_list::<S>(config, "analog_channel_id", qtype::SYMBOL_LIST, self.num_analog_channel as usize, "invalid analog channel id\0")?;
+    let status_ids = get_config_list::<S>(config, "status_channel_id", qtype::SYMBOL_LIST, self.num_status_channel as usize, "invalid status channel id\0")?;
+    let mut used = ["sample_number", "time"].iter().map(|name| name.to_string()).collect::<HashSet<String>>();
+    let analog_names = analog_ids.iter().enumerate().map(|(idx, id)| (to_column_name(S_to_str(*id), "analog"), format!("analog_channel_{}", idx)));
+    let status_names = status_ids.iter().enumerate().map(|(idx, id)| (to_column_name(S_to_str(*id), "status"), format!("status_channel_{}", idx)));
+    self.column_names = Some(analog_names.chain(status_names).map(|(name, fallback)| unique_column_name(name.unwrap_or(fallback), &mut used)).collect());
+    Ok(())
+  }
+
+  /// Convert analog data into engineering unit with `channel_multiplier` and `channel_offset_adder` of the configuration.
+  pub(crate) fn set_engineering_unit(&mut self, config: K) -> Result<(), &'static str>{
+    let multipliers = get_config_list::<E>(config, "channel_multiplier", qtype::REAL_LIST, self.num_analog_channel as usize, "invalid channel multiplier\0")?;
+    let offsets = get_config_list::<E>(config, "channel_offset_adder", qtype::REAL_LIST, self.num_analog_channel as usize, "invalid channel offset adder\0")?;
+    self.analog_conversion = Some(multipliers.iter().zip(offsets.iter()).map(|(multiplier, offset)| (*multiplier as f64, *offset as f64)).collect());
+    Ok(())
+  }
+
+  /// Refer analog data in engineering unit to the primary or secondary side with `primary_factor`, `secondary_factor`
+  ///  and `scaling_identifier` of the configuration. `set_engineering_unit` must be called before.
+  /// # Parameters
+  /// - `side`: `primary` or `secondary`.
+  pub(crate) fn set_side(&mut self, config: K, side: &str) -> Result<(), &'static str>{
+    let to_primary = match side{
+      "primary" => true,
+      "secondary" => false,
+      _ => return Err("invalid side - expected `primary or `secondary\0")
+    };
+    let conversion = match self.analog_conversion.as_mut(){
+      Some(conversion) => conversion,
+      None => return Err("engineering unit is not set\0")
+    };
+    let primary_factors = get_config_list::<E>(config, "primary_factor", qtype::REAL_LIST, conversion.len(), "invalid primary factor\0")?;
+    let secondary_factors = get_config_list::<E>(config, "secondary_factor", qtype::REAL_LIST, conversion.len(), "invalid secondary factor\0")?;
+    let scaling_identifiers = get_config_list::<G>(config, "scaling_identifier", qtype::STRING, conversion.len(), "invalid data scaling identifier\0")?;
+    // Factors and the scaling identifier missing in the 1991 revision are regarded as 1 and 'p'.
+    let factor = |factor: E| if factor.is_nan() {1.0} else {factor as f64};
+    for (idx, (multiplier, offset)) in conversion.iter_mut().enumerate(){
+      let (primary, secondary) = (factor(primary_factors[idx]), factor(secondary_factors[idx]));
+      let ratio = match (scaling_identifiers[idx], to_primary){
+        (b'p' | b' ', true) | (b's', false) => continue,
+        (b's', true) => primary / secondary,
+        (b'p' | b' ', false) => secondary / primary,
+        _ => return Err("invalid data scaling identifier\0")
+      };
+      if !ratio.is_finite() || ratio == 0.0{
+        return Err("invalid primary or secondary factor\0");
       }
+      *multiplier *= ratio;
+      *offset *= ratio;
     }
+    Ok(())
+  }
 
-    // Deserilize status data
-    for i in 2+num_analog_channel as usize..tokens.len(){
-      match tokens[i].parse::<i32>(){
-        Ok(0) => {
-          values_slice[i].push_raw(0).unwrap();
-        },
-        Ok(1) => {
-          values_slice[i].push_raw(1).unwrap();
-        },
-        _ => return Err("invalid status channel data\0")
+  /// Time of a record derived from its sample number and the sample rate table. The first sample is taken at
+  ///  `first_data_time` and each sample following a sample `n` at a rate `samp` is taken `1 / samp` seconds later.
+  ///  Samples beyond the last `endsamp` are assumed to continue at the last rate. Null is returned if the rate of the
+  ///  sample is not given.
+  fn sample_time(&self, sample_number: i32) -> i64{
+    if sample_number < 1 || self.first_data_time == qnull_base::J{
+      return qnull_base::J;
+    }
+    let sample_number = sample_number as i64;
+    // Elapsed time at the first sample of the current segment in nanoseconds.
+    let mut elapsed = 0_f64;
+    let mut first_sample = 1_i64;
+    for (idx, (rate, last_sample)) in self.sample_rates.iter().enumerate(){
+      if rate.is_nan() || *rate <= 0.0{
+        return qnull_base::J;
+      }
+      if sample_number <= *last_sample || idx + 1 == self.sample_rates.len(){
+        elapsed += (sample_number - first_sample) as f64 * 1e9 / rate;
+        return self.first_data_time + elapsed.round() as i64;
       }
+      elapsed += (*last_sample + 1 - first_sample) as f64 * 1e9 / rate;
+      first_sample = *last_sample + 1;
     }
+    qnull_base::J
+  }
 
-    Ok(())
+  /// Time of a record from its sample number and timestamp multiplied by `timestamp_multiplication_factor`. Time of a
+  ///  record whose timestamp is blank is derived from the sample rate unless timestamp is critical.
+  fn record_time(&self, sample_number: i32, timestamp: Option<i64>) -> Result<i64, &'static str>{
+    match timestamp{
+      Some(timestamp) => Ok(self.first_data_time + ((1000 * timestamp) as f64 * self.timestamp_multiplication_factor) as i64),
+      None if self.critical_timestamp => Err("invalid timestamp\0"),
+      None => Ok(self.sample_time(sample_number))
+    }
   }
-}
 
-/// Deserialize each record of data (.dat) file written in a binary format.
-/// sample number (4 bytes) + timestamp (4 bytes) + analog data (2 bytes) * num_ananalog + status data (2 * INT(num_status / 16 bits))
-fn deserialize_comtrade_data_inner_binary(chunk: &[u8], mut cursor: usize, values: K, num_analog_channel: i32, num_status_channel: i32, critical_timestamp: bool, first_data_time: i64, timestamp_multiplication_factor: f64) -> Result<usize, &'static str>{
-  
-  if (chunk.len() % (4 * 2 + 2 * num_analog_channel as usize + 2 * (num_status_channel as f64 / 16_f64).ceil() as usize)) != 0{
-    // Total length of bytes is not a multiple of single line length
-    return Err("the number of fields is fewer than expected\0");
+  /// Capacity to reserve for records decoded from `num_lines` lines of data file written in ASCII format. It is narrowed
+  ///  down by the number of records expected from `endsamp` and the sample range if known.
+  fn record_capacity(&self, num_lines: usize) -> usize{
+    let expected = match (self.expected_records, &self.record_range){
+      (Some(expected), RecordRange::Sample(first, last)) => ((*last).min(expected as i64) - (*first).max(1) + 1).max(0) as usize,
+      (Some(expected), _) => expected,
+      (None, _) => num_lines
+    };
+    expected.min(num_lines)
+  }
+
+  /// Capacity to reserve for records decoded from lines of data file written in ASCII format whose number is not known
+  ///  in advance. Nothing is reserved unless the number of records in the range is known, so that a window of a large
+  ///  file does not reserve records of the whole file.
+  fn lazy_record_capacity(&self, max_lines: usize) -> usize{
+    match (self.expected_records, &self.record_range){
+      (Some(_), RecordRange::All) | (Some(_), RecordRange::Sample(_, _)) => self.record_capacity(max_lines),
+      _ => 0
+    }
+  }
+
+  /// Size of a record of binary data file in bytes.
+  /// sample number (4 bytes) + timestamp (4 bytes) + analog data (2 or 4 bytes) * num_ananalog + status data (2 * INT(num_status / 16 bits))
+  fn binary_record_size(&self) -> usize{
+    4 * 2 + self.file_type.analog_size() * self.num_analog_channel as usize + 2 * (self.num_status_channel as f64 / 16_f64).ceil() as usize
   }
-  
-  let values_slice=values.as_mut_slice::<K>();
-  
-  // Deserialize sample number
-  values_slice[0].push_raw(i32::from_le_bytes(chunk[cursor..cursor+4].try_into().unwrap())).unwrap();
-  cursor+=4;
 
-  // Deserialize timestamp
-  if chunk[cursor..cursor+4] == [0xFF_u8; 4]{
-    if critical_timestamp{
-      return Err("invalid timestamp\0")
+  /// q type of analog data columns.
+  fn analog_qtype(&self) -> i8{
+    if self.analog_conversion.is_some(){
+      qtype::FLOAT_LIST
     }
     else{
-      values_slice[1].push_raw(qnull_base::J).unwrap();
+      self.file_type.analog_qtype()
     }
   }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Private Fucntions                           //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Sanitise a channel ID into a valid q name. Characters other than alphanumerics and '_' are replaced with '_'.
+///  `prefix` is prepended if the name does not start with a letter and "1" is appended to a q keyword.
+///  `None` is returned if the ID has no alphanumeric character.
+fn to_column_name(id: &str, prefix: &str) -> Option<String>{
+  let name = id.trim().chars().map(|c| if c.is_ascii_alphanumeric() {c} else {'_'}).collect::<String>();
+  // Collapse and trim '_'
+  let name = name.split('_').filter(|part| !part.is_empty()).collect::<Vec<&str>>().join("_");
+  if name.is_empty(){
+    None
+  }
+  else if !name.starts_with(|c: char| c.is_ascii_alphabetic()){
+    Some(format!("{}_{}", prefix, name))
+  }
+  else if Q_RESERVED_WORDS.contains(&name.as_str()){
+    Some(format!("{}1", name))
+  }
   else{
-    let num = i32::from_le_bytes(chunk[cursor..cursor+4].try_into().unwrap());
-    values_slice[1].push_raw(first_data_time + ((1000 * num as i64) as f64 * timestamp_multiplication_factor) as i64).unwrap();
+    Some(name)
   }
-  cursor+=4;
+}
 
-  // Deserilize analog data
-  chunk[cursor..cursor+2*num_analog_channel as usize].chunks(2).enumerate().for_each(|(idx, data)|{
-    if data == &[0x00_u8, 0x80]{
-      values_slice[2+idx].push_raw(qnull_base::I).unwrap();
+/// De-duplicate a column name against names in use by appending `_2`, `_3`, ... The unique name is marked as used.
+fn unique_column_name(name: String, used: &mut HashSet<String>) -> String{
+  let mut unique = name.clone();
+  let mut suffix = 2;
+  while used.contains(&unique){
+    unique = format!("{}_{}", name, suffix);
+    suffix += 1;
+  }
+  used.insert(unique.clone());
+  unique
+}
+
+/// Resolve a selection of channels given by indices (int or long) or IDs (symbol) into positions of the channels in a
+///  record. Duplicates are rejected.
+fn select_channels(selection: K, indices: &[I], ids: &[S], error: &'static str) -> Result<Vec<usize>, &'static str>{
+  let positions = match selection.get_type(){
+    qtype::COMPOUND_LIST if selection.len() == 0 => Some(Vec::new()),
+    qtype::INT_LIST => selection.as_mut_slice::<I>().iter().map(|index| indices.iter().position(|i| i == index)).collect::<Option<Vec<usize>>>(),
+    qtype::LONG_LIST => selection.as_mut_slice::<J>().iter().map(|index| indices.iter().position(|i| *i as J == *index)).collect::<Option<Vec<usize>>>(),
+    qtype::SYMBOL_LIST => selection.as_mut_slice::<S>().iter().map(|id| ids.iter().position(|i| S_to_str(*i) == S_to_str(*id))).collect::<Option<Vec<usize>>>(),
+    _ => return Err("invalid channel selection - expected list of int, long or symbol\0")
+  };
+  let positions = positions.ok_or(error)?;
+  let mut selected = HashSet::new();
+  if positions.iter().all(|position| selected.insert(*position)){
+    Ok(positions)
+  }
+  else{
+    Err("duplicate channel\0")
+  }
+}
+
+/// Write an analog data to the `row`th element of a column of the `idx`th analog channel. If conversion into
+///  engineering unit is requested, the data is converted into `multiplier * data + offset` and null is propagated.
+fn set_analog(column: K, row: usize, data: AnalogData, idx: usize, spec: &DataSpec){
+  match (data, &spec.analog_conversion){
+    (AnalogData::Int(num), None) => {
+      column.as_mut_slice::<I>()[row] = num;
+    },
+    (AnalogData::Real(num), None) => {
+      // NaN is q null real.
+      column.as_mut_slice::<E>()[row] = num;
+    },
+    (AnalogData::Int(num), Some(_)) if num == qnull_base::I => {
+      column.as_mut_slice::<F>()[row] = qnull_base::F;
+    },
+    (AnalogData::Int(num), Some(conversion)) => {
+      let (multiplier, offset) = conversion[idx];
+      column.as_mut_slice::<F>()[row] = multiplier * num as f64 + offset;
+    },
+    (AnalogData::Real(num), Some(conversion)) => {
+      // NaN is propagated.
+      let (multiplier, offset) = conversion[idx];
+      column.as_mut_slice::<F>()[row] = multiplier * num as f64 + offset;
     }
-    else{
-      let num = i16::from_le_bytes(data.try_into().unwrap());
-      values_slice[2+idx].push_raw(num as i32).unwrap();
+  }
+}
+
+/// Decode each line of data (.dat) file written in ASCII format into native buffers. `tokens` is a buffer reused
+///  across lines.
+/// "n, timestamp, A1, A2,···Ak, D1, D2,···Dm"
+fn deserialize_comtrade_data_inner_ascii<'a>(line: &'a str, tokens: &mut Vec<&'a str>, records: &mut AsciiRecords, spec: &DataSpec) -> Result<(), ComtradeError>{
+  let num_analog_channel = spec.num_analog_channel as usize;
+  let num_status_channel = spec.num_status_channel as usize;
+  tokens.clear();
+  tokens.extend(line.split(','));
+  if tokens.len() != num_analog_channel + num_status_channel + 2{
+    return Err("the number of fields is fewer than expected\0".into());
+  }
+
+  // Deserialize sample number
+  let sample_number = match tokens[0].parse::<i32>(){
+    Ok(num) => num,
+    Err(_) => return Err(ComtradeError::from("invalid sample number\0").at_field(0, tokens[0]))
+  };
+
+  // Deserialize timestamp. Time is derived from the sample rate if timestamp is blank.
+  let time = spec.record_time(sample_number, tokens[1].parse::<i64>().ok()).map_err(|error| ComtradeError::from(error).at_field(1, tokens[1]))?;
+
+  // Deserilize analog data. Channels which are not selected are skipped.
+  for (column, channel) in records.analog.iter_mut().zip(spec.analog_channels.iter()){
+    match tokens[2+channel].parse::<i32>(){
+      Ok(99999) => column.push(qnull_base::I),
+      Ok(num) => column.push(num),
+      Err(_) => return Err(ComtradeError::from("invalid analog channel data\0").at_field(2+channel, tokens[2+channel]))
     }
-    cursor+=2;
-  });
+  }
 
-  // final block may not be complete 16 bits but padded.
-  let complete_chunk_size = num_status_channel / 16;
-  chunk[cursor..cursor + 2*complete_chunk_size as usize].chunks(2).enumerate().for_each(|(idx, data)|{
-    // 16 channel data are stored in 2 bytes in Little Endian
-    // 8th - 1st | 16th - 9th
-    let offset = 16 * idx;
-    let view = data.view_bits::<Msb0>();
-    for i in 2 .. 10{
-      // Higher bits
-      // ex.) values_slice[2+num_analog_channel as usize + offset + 0].push_raw(view[7]).unwrap();
-      // values_slice[2+num_analog_channel as usize + offset + 7].push_raw(view[0]).unwrap();
-      values_slice[i + num_analog_channel as usize + offset].push_raw(view[9-i]).unwrap();
-    }
-    for i in 10..18{
-      // Lower bits
-      // ex.) values_slice[2+num_analog_channel as usize + offset + 8].push_raw(view[15]).unwrap();
-      // values_slice[2+num_analog_channel as usize + offset + 15].push_raw(view[8]).unwrap();
-      values_slice[i + num_analog_channel as usize + offset].push_raw(view[25-i]).unwrap();
-    }
-    cursor+=2;
-  });
-  
-  if num_status_channel % 16 != 0{
-    // Extra chunk exist
-    let extra_chunk_size = num_status_channel as usize % 16;
-    let view = chunk[cursor .. cursor+2].view_bits::<Msb0>();
-    let mut column_offset = 2 + num_analog_channel as usize + 16 * complete_chunk_size as usize;
-    if extra_chunk_size > 8{
-      // 8th - 1st | 16th - 9th
-      for i in 0..8{
-        // Higher bits
-        values_slice[column_offset+i].push_raw(view[7-i]).unwrap();
-      }
-      column_offset+=8;
-      for i in 0 .. extra_chunk_size - 8{
-        // Lower bits
-        values_slice[column_offset+i].push_raw(view[15-i]).unwrap();
-      }
+  // Deserilize status data
+  for (column, channel) in records.status.iter_mut().zip(spec.status_channels.iter()){
+    match tokens[2+num_analog_channel+channel].parse::<i32>(){
+      Ok(0) => column.push(false),
+      Ok(1) => column.push(true),
+      _ => return Err(ComtradeError::from("invalid status channel data\0").at_field(2+num_analog_channel+channel, tokens[2+num_analog_channel+channel]))
     }
-    else{
-      // 8th - 1st | 16th - 9th
-      for i in 0..extra_chunk_size{
-        // Lower bits
-        values_slice[column_offset+i].push_raw(view[7-i]).unwrap();
+  }
+
+  records.sample_numbers.push(sample_number);
+  records.times.push(time);
+  Ok(())
+}
+
+/// Decode a block of lines of data file written in ASCII format. Decoding stops at the first invalid record or once a
+///  record follows the record range. `first_line` is the number of lines preceding the block to locate an error.
+fn decode_ascii_lines<'a, I>(lines: I, first_line: usize, capacity: usize, spec: &DataSpec) -> AsciiRecords where I: Iterator<Item = &'a str>{
+  let mut records = AsciiRecords::new(spec, capacity);
+  let mut tokens = Vec::new();
+  for (idx, line) in lines.enumerate(){
+    let result = match compare_ascii_record(line, spec){
+      Ok(Ordering::Less) => continue,
+      Ok(Ordering::Greater) => {
+        records.reached_end = true;
+        break;
+      },
+      Ok(Ordering::Equal) => deserialize_comtrade_data_inner_ascii(line, &mut tokens, &mut records, spec),
+      Err(error) => Err(error)
+    };
+    if let Err(error) = result{
+      records.error = Some(error.at_line(first_line + idx + 1));
+      break;
+    }
+  }
+  records
+}
+
+/// Compare a line of data file written in ASCII format with the record range without deserializing channel data.
+///  `Ordering::Less` is returned if the record precedes the range and `Ordering::Greater` if it follows the range.
+fn compare_ascii_record(line: &str, spec: &DataSpec) -> Result<Ordering, ComtradeError>{
+  let (first, last) = match spec.record_range{
+    RecordRange::All => return Ok(Ordering::Equal),
+    RecordRange::Sample(first, last) | RecordRange::Time(first, last) => (first, last)
+  };
+  let mut tokens = line.split(',');
+  let token = tokens.next().unwrap_or_default();
+  let sample_number = match token.parse::<i32>(){
+    Ok(num) => num,
+    Err(_) => return Err(ComtradeError::from("invalid sample number\0").at_field(0, token))
+  };
+  let key = match spec.record_range{
+    RecordRange::Time(_, _) => {
+      let token = tokens.next().unwrap_or_default();
+      spec.record_time(sample_number, token.parse::<i64>().ok()).map_err(|error| ComtradeError::from(error).at_field(1, token))?
+    },
+    _ => sample_number as i64
+  };
+  Ok(compare_to_range(key, first, last))
+}
+
+/// Compare a sample number or time with an inclusive range.
+fn compare_to_range(key: i64, first: i64, last: i64) -> Ordering{
+  if key < first{
+    Ordering::Less
+  }
+  else if key > last{
+    Ordering::Greater
+  }
+  else{
+    Ordering::Equal
+  }
+}
+
+/// Timestamp of a binary record. `None` is returned for the blank timestamp 0xFFFFFFFF.
+fn binary_timestamp(bytes: &[u8]) -> Option<i64>{
+  if bytes == [0xFF_u8; 4]{
+    None
+  }
+  else{
+    Some(i32::from_le_bytes(bytes.try_into().unwrap()) as i64)
+  }
+}
+
+/// Indices of the first record to load and the record following the last one in a data file written in a binary
+///  format. Records are located by binary search over sample numbers or timestamps read at the offsets computed from
+///  the fixed record size. Records are assumed to be sorted by sample number and time.
+fn binary_record_bounds(bytes: &[u8], spec: &DataSpec) -> Result<(usize, usize), ComtradeError>{
+  let record_size = spec.binary_record_size();
+  let num_records = bytes.len() / record_size;
+  let (first, last) = match spec.record_range{
+    RecordRange::All => return Ok((0, num_records)),
+    RecordRange::Sample(first, last) | RecordRange::Time(first, last) => (first, last)
+  };
+  let compare_record = |idx: usize| -> Result<Ordering, ComtradeError>{
+    let record = &bytes[idx*record_size..(idx+1)*record_size];
+    let sample_number = i32::from_le_bytes(record[0..4].try_into().unwrap());
+    let key = match spec.record_range{
+      RecordRange::Time(_, _) => spec.record_time(sample_number, binary_timestamp(&record[4..8])).map_err(|error| ComtradeError::from(error).at_record(idx + 1))?,
+      _ => sample_number as i64
+    };
+    Ok(compare_to_range(key, first, last))
+  };
+  // Find the first record which is not before the range and the first record after the range.
+  let mut bounds = [0, 0];
+  for (bound, preceding) in bounds.iter_mut().zip([Ordering::Less, Ordering::Equal].iter()){
+    let (mut low, mut high) = (0, num_records);
+    while low < high{
+      let middle = low + (high - low) / 2;
+      if compare_record(middle)? <= *preceding{
+        low = middle + 1;
+      }
+      else{
+        high = middle;
       }
     }
-    cursor+=2;
+    *bound = low;
   }
+  Ok((bounds[0], bounds[1]))
+}
+
+/// Deserialize each record of data (.dat) file written in a binary format into the `row`th element of columns.
+/// sample number (4 bytes) + timestamp (4 bytes) + analog data (2 or 4 bytes) * num_ananalog + status data (2 * INT(num_status / 16 bits))
+fn deserialize_comtrade_data_inner_binary(chunk: &[u8], mut cursor: usize, row: usize, values: K, spec: &DataSpec) -> Result<usize, &'static str>{
+  
+  let num_analog_channel = spec.num_analog_channel;
+  let num_status_channel = spec.num_status_channel;
+  let analog_size = spec.file_type.analog_size();
   
+  let values_slice=values.as_mut_slice::<K>();
+  
+  // Deserialize sample number
+  let sample_number = i32::from_le_bytes(chunk[cursor..cursor+4].try_into().unwrap());
+  values_slice[0].as_mut_slice::<I>()[row] = sample_number;
+  cursor+=4;
+
+  // Deserialize timestamp. Time is derived from the sample rate if timestamp is blank.
+  values_slice[1].as_mut_slice::<J>()[row] = spec.record_time(sample_number, binary_timestamp(&chunk[cursor..cursor+4]))?;
+  cursor+=4;
+
+  // Deserilize analog data. Channels which are not selected are skipped.
+  for (column, channel) in spec.analog_channels.iter().enumerate(){
+    let data = &chunk[cursor+analog_size*channel..cursor+analog_size*(channel+1)];
+    let analog_data = match spec.file_type{
+      DataFileType::Binary32 => {
+        // 0x80000000 is a null sentinel which coincides with q null int.
+        AnalogData::Int(i32::from_le_bytes(data.try_into().unwrap()))
+      },
+      DataFileType::Float32 => {
+        AnalogData::Real(f32::from_le_bytes(data.try_into().unwrap()))
+      },
+      _ => {
+        if data == [0x00_u8, 0x80]{
+          AnalogData::Int(qnull_base::I)
+        }
+        else{
+          AnalogData::Int(i16::from_le_bytes(data.try_into().unwrap()) as i32)
+        }
+      }
+    };
+    set_analog(values_slice[2+column], row, analog_data, *channel, spec);
+  }
+  cursor+=analog_size*num_analog_channel as usize;
+
+  // 16 channel data are stored in 2 bytes in Little Endian, i.e., Nth channel is Nth bit from the lowest bit of the
+  //  first byte. Final block may not be complete 16 bits but padded.
+  let status_size = 2 * (num_status_channel as f64 / 16_f64).ceil() as usize;
+  let view = chunk[cursor..cursor+status_size].view_bits::<Lsb0>();
+  let column_offset = 2 + spec.analog_channels.len();
+  for (column, channel) in spec.status_channels.iter().enumerate(){
+    values_slice[column_offset+column].as_mut_slice::<G>()[row] = view[*channel] as G;
+  }
+  cursor+=status_size;
 
   Ok(cursor)
 
 }
 
-//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
-//                               Interface                              //
-//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
-/// Deserialize the data file (`.dat`) of COMTRADE format into q table.
-/// # Parameters
-/// - `data`: 
-///   - symbol: File path which starts with `:`. Thsi file must be delimited <CR/LF>. i.e., the file must be in the Windows format.
-///   - string: File contents.
-///   - list of byte: File contents
-/// - `num_analog_channel_`: The number of analog channels.
-/// - `num_status_channel_`: The number of status channels.
-/// - `critical_timestamp_`: Flag of whether timestamp is critical or not.
-/// - `first_data_time_`: Timestamp of the first data.
-/// - `timestamp_multiplication_factor_`: Multiplication factor for timestamp in each record. Timestamp of each record is
-///  `first_data_time` + timestamp * `timestamp_multiplication_factor_`.
-/// - `is_ascii`: Flag of whether data is encoded in ASCI or binary.
-#[no_mangle]
-pub extern "C" fn deserialize_comtrade_data(data: K, num_analog_channel_: K, num_status_channel_: K, critical_timestamp_: K, first_data_time_: K, timestamp_multiplication_factor_: K, is_ascii_: K) -> K{
-
-  let num_analog_channel = num_analog_channel_.get_int().unwrap();
-  let num_status_channel = num_status_channel_.get_int().unwrap();
-  let critical_timestamp = critical_timestamp_.get_bool().unwrap();
-  let first_data_time = first_data_time_.get_long().unwrap();
-  let timestamp_multiplication_factor = timestamp_multiplication_factor_.get_real().unwrap() as f64;
-  let is_ascii = is_ascii_.get_bool().unwrap();
+/// Prepare column names and columns of a data table allocated for `num_records` records. Only selected channels have a
+///  column.
+fn new_data_columns(spec: &DataSpec, num_records: usize) -> (K, K){
+  let num_analog_channel = spec.num_analog_channel as usize;
 
   // Prepare keys
-  let keys=new_list(qtype::SYMBOL_LIST, (2 + num_analog_channel + num_status_channel) as J);
+  let keys=new_list(qtype::SYMBOL_LIST, (2 + spec.analog_channels.len() + spec.status_channels.len()) as J);
   let keys_slice=keys.as_mut_slice::<S>();
   keys_slice[0]=internalize(str_to_S!("sample_number"));
   keys_slice[1]=internalize(str_to_S!("time"));
-  for i in 2..2+num_analog_channel as usize{
-    keys_slice[i]=internalize(str_to_S!(format!("analog_channel_{}", i-2).as_str()));
-  }
-  for i in (2+num_analog_channel) as usize..(2+num_analog_channel+num_status_channel) as usize{
-    keys_slice[i]=internalize(str_to_S!(format!("status_channel_{}", i-2-num_analog_channel as usize).as_str()));
+  let analog_names = spec.analog_channels.iter().map(|channel| match &spec.column_names{
+    Some(column_names) => column_names[*channel].clone(),
+    None => format!("analog_channel_{}", channel)
+  });
+  let status_names = spec.status_channels.iter().map(|channel| match &spec.column_names{
+    Some(column_names) => column_names[num_analog_channel+channel].clone(),
+    None => format!("status_channel_{}", channel)
+  });
+  for (key, name) in keys_slice[2..].iter_mut().zip(analog_names.chain(status_names)){
+    *key=internalize(str_to_S!(name.as_str()));
   }
 
   // Prepare values
   let mut values=new_list(qtype::COMPOUND_LIST, 2);
-  values.as_mut_slice::<K>().copy_from_slice(&[new_list(qtype::INT_LIST, 0), new_list(qtype::TIMESTAMP_LIST, 0)]);
-  for _ in 0..num_analog_channel as usize{
-    values.push(new_list(qtype::INT_LIST, 0)).unwrap();
+  values.as_mut_slice::<K>().copy_from_slice(&[new_list(qtype::INT_LIST, num_records as J), new_list(qtype::TIMESTAMP_LIST, num_records as J)]);
+  for _ in 0..spec.analog_channels.len(){
+    values.push(new_list(spec.analog_qtype(), num_records as J)).unwrap();
   }
-  for _ in 0..num_status_channel as usize{
-    values.push(new_list(qtype::BOOL_LIST, 0)).unwrap();
+  for _ in 0..spec.status_channels.len(){
+    values.push(new_list(qtype::BOOL_LIST, num_records as J)).unwrap();
   }
 
-  if is_ascii{
-    // Process ASCII data
-    let string;
-    let mut contents_buffer = String::new();
-    if data.get_type() == qtype::SYMBOL_ATOM{
-      if let Err(error) = load_ascii_data(data, &mut contents_buffer){
-        return new_error(error);
+  (keys, values)
+}
+
+/// Interpolate analog data sampled at `time + skew` onto `time`. Instants outside the sampled range or whose
+///  neighbouring sample times are null are filled as null.
+fn interpolate_skewed_column(time: &[i64], values: &[f64], skew: i64) -> Vec<f64>{
+  let mut interpolated = vec![qnull_base::F; values.len()];
+  if skew == 0{
+    interpolated.copy_from_slice(values);
+    return interpolated;
+  }
+  // Index of the latest sample taken at or before the target instant.
+  let mut cursor = 0;
+  for (idx, target) in time.iter().enumerate(){
+    if *target == qnull_base::J{
+      continue;
+    }
+    while cursor + 1 < time.len() && time[cursor+1] != qnull_base::J && time[cursor+1] + skew <= *target{
+      cursor += 1;
+    }
+    let sampled = time[cursor] + skew;
+    if time[cursor] == qnull_base::J || sampled > *target{
+      continue;
+    }
+    if sampled == *target{
+      interpolated[idx] = values[cursor];
+    }
+    else if cursor + 1 < time.len() && time[cursor+1] != qnull_base::J{
+      let next_sampled = time[cursor+1] + skew;
+      let ratio = (*target - sampled) as f64 / (next_sampled - sampled) as f64;
+      interpolated[idx] = values[cursor] + (values[cursor+1] - values[cursor]) * ratio;
+    }
+  }
+  interpolated
+}
+
+/// Build a data table from columns applying skew correction.
+fn new_data_table(mut keys: K, values: K, spec: &DataSpec) -> K{
+  match &spec.skew_correction{
+    SkewCorrection::None => (),
+    SkewCorrection::TimeColumn(skews) => {
+      let mut values = values;
+      let time = values.as_mut_slice::<K>()[1].as_mut_slice::<J>();
+      // A time column is de-duplicated against channel columns, e.g. a channel named `time_ia`.
+      let mut used = keys.as_mut_slice::<S>().iter().map(|key| S_to_str(*key).to_string()).collect::<HashSet<String>>();
+      for (column, channel) in spec.analog_channels.iter().enumerate(){
+        let skew = skews[*channel];
+        let name = unique_column_name(format!("time_{}", S_to_str(keys.as_mut_slice::<S>()[2+column])), &mut used);
+        let corrected = new_list(qtype::TIMESTAMP_LIST, time.len() as J);
+        corrected.as_mut_slice::<J>().iter_mut().zip(time.iter()).for_each(|(corrected, time)|{
+          *corrected = if *time == qnull_base::J {qnull_base::J} else {*time + skew};
+        });
+        keys.push_symbol(name.as_str()).unwrap();
+        values.push(corrected).unwrap();
       }
-      else{
-        string=contents_buffer.as_str();
+      return flip(new_dictionary(keys, values));
+    },
+    SkewCorrection::Interpolate(skews) => {
+      let values_slice = values.as_mut_slice::<K>();
+      let time = values_slice[1].as_mut_slice::<J>();
+      for (column, channel) in spec.analog_channels.iter().enumerate(){
+        let column = values_slice[2+column].as_mut_slice::<F>();
+        let interpolated = interpolate_skewed_column(time, column, skews[*channel]);
+        column.copy_from_slice(&interpolated);
       }
     }
-    else{
-      match data.get_str(){
-        Ok(string_) => {
-          string = string_;
-        },
-        Err(error) => {
-          decrement_reference_count(keys);
-          decrement_reference_count(values);
-          return new_error(error)
-        }
-      }
+  }
+  flip(new_dictionary(keys, values))
+}
+
+/// Deserialize contents of the data file (`.dat`) written in ASCII format into q table.
+pub(crate) fn deserialize_comtrade_data_ascii(string: &str, spec: &DataSpec) -> Result<K, ComtradeError>{
+  let blocks = if spec.threads > 1{
+    let lines=split_lines(string);
+    // Decode blocks of lines on worker threads into native buffers. No more threads than lines are spawned.
+    let threads = spec.threads.min(lines.len()).max(1);
+    let block_size = (lines.len() as f64 / threads as f64).ceil().max(1.0) as usize;
+    std::thread::scope(|scope|{
+      let handles = lines.chunks(block_size).enumerate().map(|(idx, block)| scope.spawn(move || decode_ascii_lines(block.iter().copied(), idx * block_size, spec.record_capacity(block.len()), spec))).collect::<Vec<_>>();
+      handles.into_iter().map(|handle| handle.join().map_err(|_| ComtradeError::from("failed to decode data on a worker thread\0"))).collect::<Result<Vec<AsciiRecords>, ComtradeError>>()
+    })?
+  }
+  else{
+    // Lines are read lazily so that reading stops at the end of the record range. Trailing empty lines are ignored.
+    let string = string.trim_end_matches(['\r', '\n']);
+    vec![decode_ascii_lines(Lines::new(string), 0, spec.lazy_record_capacity(string.len() / 2 + 1), spec)]
+  };
+  new_ascii_data_table(&blocks, spec)
+}
+
+/// Build a data table from blocks of records decoded from data file written in ASCII format. The first error is
+///  returned unless it follows the end of the record range.
+fn new_ascii_data_table(blocks: &[AsciiRecords], spec: &DataSpec) -> Result<K, ComtradeError>{
+  // Count records up to the end of the record range.
+  let mut num_records = 0;
+  let mut num_blocks = 0;
+  for records in blocks.iter(){
+    if let Some(error) = &records.error{
+      return Err(error.clone());
     }
+    num_records += records.len();
+    num_blocks += 1;
+    if records.reached_end{
+      break;
+    }
+  }
+
+  // Build q lists on the main thread as kdb+ API is not thread-safe. Columns are allocated at once.
+  let (keys, values) = new_data_columns(spec, num_records);
+  let mut row = 0;
+  for records in blocks[..num_blocks].iter(){
+    records.write_to(values, row, spec);
+    row += records.len();
+  }
+  Ok(new_data_table(keys, values, spec))
+}
 
-    let lines=string.split_terminator("\r\n").collect::<Vec<&str>>();
-    // Deserilize each line in the data
-    for i in 0..lines.len(){
-      if let Err(error) = deserialize_comtrade_data_inner_ascii(lines[i], values, num_analog_channel, num_status_channel, critical_timestamp, first_data_time, timestamp_multiplication_factor){
+/// Deserialize contents of the data file (`.dat`) written in a binary format into q table.
+#[allow(clippy::manual_is_multiple_of)]
+pub(crate) fn deserialize_comtrade_data_binary(bytes: &[u8], spec: &DataSpec) -> Result<K, ComtradeError>{
+  let record_size = spec.binary_record_size();
+  if bytes.len() % record_size != 0{
+    // Total length of bytes is not a multiple of single line length
+    return Err(ComtradeError::from("the number of fields is fewer than expected\0").at_record(bytes.len() / record_size + 1));
+  }
+  // Seek the records within the record range.
+  let (first, last) = binary_record_bounds(bytes, spec)?;
+  deserialize_comtrade_data_binary_records(bytes, first, last, spec)
+}
+
+/// Deserialize records from the `first`th to the one before the `last`th of data file written in a binary format into
+///  q table.
+fn deserialize_comtrade_data_binary_records(bytes: &[u8], first: usize, last: usize, spec: &DataSpec) -> Result<K, ComtradeError>{
+  let record_size = spec.binary_record_size();
+  // The number of records is known from the fixed record size. Columns are allocated at once.
+  let (keys, values) = new_data_columns(spec, last - first);
+  let total=last*record_size;
+  let mut cursor=first*record_size;
+  let mut row=0;
+  while cursor < total{
+    match deserialize_comtrade_data_inner_binary(bytes, cursor, row, values, spec){
+      Ok(cursor_) => {
+        cursor = cursor_;
+        row += 1;
+      },
+      Err(error) => {
         decrement_reference_count(keys);
         decrement_reference_count(values);
-        return new_error(error);
+        return Err(ComtradeError::from(error).at_record(cursor / record_size + 1));
       }
     }
-    flip(new_dictionary(keys, values))
+  }
+  Ok(new_data_table(keys, values, spec))
+}
+
+/// Deserialize contents of the data file (`.dat`) into q table according to the file type.
+pub(crate) fn deserialize_comtrade_data_contents(bytes: &[u8], spec: &DataSpec) -> Result<K, ComtradeError>{
+  if spec.file_type == DataFileType::Ascii{
+    match std::str::from_utf8(bytes){
+      Ok(string) => deserialize_comtrade_data_ascii(string, spec),
+      Err(_) => Err("invalid UTF-8 data\0".into())
+    }
+  }
+  else{
+    deserialize_comtrade_data_binary(bytes, spec)
+  }
+}
+
+/// Call a q function with a partial table. An error raised by the function is returned as an error object.
+fn apply_data_chunk(function: K, table: K) -> Result<(), K>{
+  let args = new_list(qtype::COMPOUND_LIST, 1);
+  args.as_mut_slice::<K>()[0] = table;
+  let result = unsafe{native::ee(native::dot(function, args))};
+  decrement_reference_count(args);
+  if is_error(result){
+    Err(result)
+  }
+  else{
+    decrement_reference_count(result);
+    Ok(())
+  }
+}
+
+/// Deserialize data file (`.dat`) written in ASCII format into tables of `chunk_size` records and pass each of them to
+///  a q function. Returns the number of records.
+fn deserialize_comtrade_data_ascii_in_chunks(string: &str, chunk_size: usize, function: K, path: K, spec: &DataSpec) -> Result<J, K>{
+  let mut num_records = 0;
+  let mut chunk = Vec::with_capacity(chunk_size);
+  // Empty lines are invalid unless they trail.
+  let mut empty_lines = 0;
+  // The number of lines read so far and lines preceding the current chunk to locate an error.
+  let mut lines_read = 0;
+  let mut first_line = 0;
+  let mut lines = Lines::new(string);
+  loop{
+    let line = lines.next();
+    match line{
+      Some("") => {
+        empty_lines += 1;
+        lines_read += 1;
+        continue;
+      },
+      Some(line) => {
+        if chunk.is_empty(){
+          first_line = lines_read - empty_lines;
+        }
+        chunk.resize(chunk.len() + empty_lines, "");
+        empty_lines = 0;
+        chunk.push(line);
+        lines_read += 1;
+      },
+      None => ()
+    }
+    if chunk.len() >= chunk_size || (line.is_none() && !chunk.is_empty()){
+      let records = decode_ascii_lines(chunk.iter().copied(), first_line, spec.record_capacity(chunk.len()), spec);
+      num_records += records.len() as J;
+      let table = new_ascii_data_table(&[records], spec).map_err(|error| error.in_source(path).to_q_error())?;
+      apply_data_chunk(function, table)?;
+      chunk.clear();
+    }
+    if line.is_none(){
+      return Ok(num_records);
+    }
+  }
+}
+
+/// Deserialize data file (`.dat`) written in a binary format into tables of `chunk_size` records and pass each of them
+///  to a q function. Records of each chunk are located from the fixed record size. Returns the number of records.
+#[allow(clippy::manual_is_multiple_of)]
+fn deserialize_comtrade_data_binary_in_chunks(bytes: &[u8], chunk_size: usize, function: K, path: K, spec: &DataSpec) -> Result<J, K>{
+  let record_size = spec.binary_record_size();
+  if bytes.len() % record_size != 0{
+    // Total length of bytes is not a multiple of single line length
+    return Err(ComtradeError::from("the number of fields is fewer than expected\0").at_record(bytes.len() / record_size + 1).in_source(path).to_q_error());
+  }
+  let num_records = bytes.len() / record_size;
+  for first in (0..num_records).step_by(chunk_size){
+    let table = deserialize_comtrade_data_binary_records(bytes, first, num_records.min(first + chunk_size), spec).map_err(|error| error.in_source(path).to_q_error())?;
+    apply_data_chunk(function, table)?;
+  }
+  Ok(num_records as J)
+}
+
+/// Load the data file (`.dat`) from a file path or contents and deserialize it into q table.
+fn deserialize_comtrade_data_from(data: K, spec: &DataSpec) -> K{
+  let result = if spec.file_type == DataFileType::Ascii{
+    // Process ASCII data
+    let string;
+    let mut contents_buffer = ContentsBuffer::default();
+    // Load data into string.
+    set_string!(data, string, contents_buffer);
+    deserialize_comtrade_data_ascii(string, spec)
   }
   else{
     // Process binary data
     let bytes;
-    let mut contents_buffer = Vec::new();
+    let mut contents_buffer = ContentsBuffer::default();
     // Load data into bytes.
     set_bytes!(data, bytes, contents_buffer);
+    deserialize_comtrade_data_binary(bytes, spec)
+  };
+
+  match result{
+    Ok(table) => table,
+    Err(error) => error.in_source(data).to_q_error()
+  }
+}
+
+/// Get columns of a data table checking them against the layout of the data file. Columns are identified by position,
+///  i.e., sample number, time, analog channels of `analog_qtype` and status channels.
+fn get_data_columns<'a>(table: K, analog_qtype: i8, spec: &DataSpec) -> Result<&'a mut [K], &'static str>{
+  if table.get_type() != qtype::TABLE{
+    return Err("not a table\0");
+  }
+  let columns = table.get_dictionary()?.as_mut_slice::<K>()[1].as_mut_slice::<K>();
+  let num_analog_channel = spec.num_analog_channel as usize;
+  if columns.len() != 2 + num_analog_channel + spec.num_status_channel as usize{
+    return Err("the number of columns does not match the number of channels\0");
+  }
+  if columns[0].get_type() != qtype::INT_LIST{
+    return Err("invalid sample number column\0");
+  }
+  if columns[1].get_type() != qtype::TIMESTAMP_LIST{
+    return Err("invalid time column\0");
+  }
+  if columns[2..2+num_analog_channel].iter().any(|column| column.get_type() != analog_qtype){
+    return Err("invalid analog channel column\0");
+  }
+  if columns[2+num_analog_channel..].iter().any(|column| column.get_type() != qtype::BOOL_LIST){
+    return Err("invalid status channel column\0");
+  }
+  Ok(columns)
+}
 
-    let total=bytes.len();
-    let mut cursor=0;
-    loop{
-      match deserialize_comtrade_data_inner_binary(bytes, cursor, values, num_analog_channel, num_status_channel, critical_timestamp, first_data_time, timestamp_multiplication_factor){
-        Ok(cursor_) => {
-          if cursor_ < total{
-            cursor = cursor_;
+/// Convert time of a record into the timestamp field, i.e., (time - `first_data_time`) / `timestamp_multiplication_factor`
+///  in microseconds. `None` is returned for null time if timestamp is not critical.
+fn serialize_comtrade_data_timestamp(time: i64, max: i64, spec: &DataSpec) -> Result<Option<i64>, &'static str>{
+  if time == qnull_base::J{
+    if spec.critical_timestamp{
+      return Err("invalid timestamp\0");
+    }
+    return Ok(None);
+  }
+  let timestamp = ((time - spec.first_data_time) as f64 / (1000.0 * spec.timestamp_multiplication_factor)).round() as i64;
+  if timestamp < 0 || timestamp > max{
+    Err("timestamp out of range\0")
+  }
+  else{
+    Ok(Some(timestamp))
+  }
+}
+
+/// Serialize data columns into contents of the data file (`.dat`) written in ASCII format. Null analog data is written
+///  as 99999 and null time is written as an empty field.
+/// "n, timestamp, A1, A2,···Ak, D1, D2,···Dm"
+fn serialize_comtrade_data_ascii(columns: &[K], spec: &DataSpec) -> Result<Vec<u8>, &'static str>{
+  let num_analog_channel = spec.num_analog_channel as usize;
+  let sample_numbers = columns[0].as_mut_slice::<I>();
+  let times = columns[1].as_mut_slice::<J>();
+  let analog_columns = columns[2..2+num_analog_channel].iter().map(|column| &*column.as_mut_slice::<I>()).collect::<Vec<&[I]>>();
+  let status_columns = columns[2+num_analog_channel..].iter().map(|column| &*column.as_mut_slice::<G>()).collect::<Vec<&[G]>>();
+
+  let mut contents = String::new();
+  for (row, sample_number) in sample_numbers.iter().enumerate(){
+    if *sample_number == qnull_base::I{
+      return Err("invalid sample number\0");
+    }
+    contents.push_str(&sample_number.to_string());
+    contents.push(',');
+    if let Some(timestamp) = serialize_comtrade_data_timestamp(times[row], 9_999_999_999, spec)?{
+      contents.push_str(&timestamp.to_string());
+    }
+    for column in analog_columns.iter(){
+      contents.push(',');
+      match column[row]{
+        qnull_base::I => contents.push_str("99999"),
+        // 99999 is reserved for missing data.
+        num if (-99999..99999).contains(&num) => contents.push_str(&num.to_string()),
+        _ => return Err("analog data out of range\0")
+      }
+    }
+    for column in status_columns.iter(){
+      contents.push(',');
+      contents.push(if column[row] == 0 {'0'} else {'1'});
+    }
+    contents.push_str("\r\n");
+  }
+  Ok(contents.into_bytes())
+}
+
+/// Serialize data columns into contents of the data file (`.dat`) written in a binary format. Null analog data and null
+///  time are written as the sentinels of the file type.
+/// sample number (4 bytes) + timestamp (4 bytes) + analog data (2 or 4 bytes) * num_ananalog + status data (2 * INT(num_status / 16 bits))
+fn serialize_comtrade_data_binary(columns: &[K], spec: &DataSpec) -> Result<Vec<u8>, &'static str>{
+  let num_analog_channel = spec.num_analog_channel as usize;
+  let sample_numbers = columns[0].as_mut_slice::<I>();
+  let times = columns[1].as_mut_slice::<J>();
+  let status_columns = columns[2+num_analog_channel..].iter().map(|column| &*column.as_mut_slice::<G>()).collect::<Vec<&[G]>>();
+  let record_size = 4 * 2 + spec.file_type.analog_size() * num_analog_channel + 2 * (spec.num_status_channel as f64 / 16_f64).ceil() as usize;
+
+  let mut bytes = Vec::with_capacity(record_size * sample_numbers.len());
+  for (row, sample_number) in sample_numbers.iter().enumerate(){
+    if *sample_number == qnull_base::I{
+      return Err("invalid sample number\0");
+    }
+    bytes.extend_from_slice(&sample_number.to_le_bytes());
+    match serialize_comtrade_data_timestamp(times[row], i32::MAX as i64, spec)?{
+      Some(timestamp) => bytes.extend_from_slice(&(timestamp as i32).to_le_bytes()),
+      None => bytes.extend_from_slice(&[0xFF_u8; 4])
+    }
+
+    for column in columns[2..2+num_analog_channel].iter(){
+      match spec.file_type{
+        DataFileType::Binary32 => {
+          // q null int coincides with the null sentinel 0x80000000.
+          bytes.extend_from_slice(&column.as_mut_slice::<I>()[row].to_le_bytes());
+        },
+        DataFileType::Float32 => {
+          // NaN is regarded as null.
+          bytes.extend_from_slice(&column.as_mut_slice::<E>()[row].to_le_bytes());
+        },
+        _ => {
+          match column.as_mut_slice::<I>()[row]{
+            qnull_base::I => bytes.extend_from_slice(&[0x00_u8, 0x80]),
+            // 0x8000 is reserved for missing data.
+            num if (-32767..=32767).contains(&num) => bytes.extend_from_slice(&(num as i16).to_le_bytes()),
+            _ => return Err("analog data out of range\0")
           }
-          else{
-            break;
+        }
+      }
+    }
+
+    // 16 channel data are stored in 2 bytes in Little Endian. Nth channel of each block is Nth bit from the lowest.
+    // The last block is padded with 0.
+    for block in status_columns.chunks(16){
+      let word = block.iter().enumerate().fold(0_u16, |word, (bit, column)| if column[row] == 0 {word} else {word | (1 << bit)});
+      bytes.extend_from_slice(&word.to_le_bytes());
+    }
+  }
+  Ok(bytes)
+}
+
+/// Serialize data columns into contents of the data file (`.dat`) according to the file type.
+fn serialize_comtrade_data_columns(columns: &[K], spec: &DataSpec) -> Result<Vec<u8>, &'static str>{
+  if spec.file_type == DataFileType::Ascii{
+    serialize_comtrade_data_ascii(columns, spec)
+  }
+  else{
+    serialize_comtrade_data_binary(columns, spec)
+  }
+}
+
+/// Serialize a data table into contents of the data file (`.dat`) according to the file type.
+pub(crate) fn serialize_comtrade_data_contents(table: K, spec: &DataSpec) -> Result<Vec<u8>, &'static str>{
+  serialize_comtrade_data_columns(get_data_columns(table, spec.file_type.analog_qtype(), spec)?, spec)
+}
+
+/// Choose a multiplier `a` and an offset `b` which map the range of `values` onto `range` so that `a * data + b` restores
+///  values at the maximum resolution, and quantise `values` into data. Factors are rounded to real as they are stored in
+///  the configuration. Returns the factors, data and minimum and maximum of the data. NaN is quantised into null.
+fn quantise_analog_column(values: &[f64], range: (i32, i32)) -> (f32, f32, Vec<i32>, i32, i32){
+  let (min, max) = values.iter().filter(|value| value.is_finite()).fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| (min.min(*value), max.max(*value)));
+  let (multiplier, offset) = if min > max{
+    // No data
+    (1.0, 0.0)
+  }
+  else if min == max{
+    (1.0, min as f32)
+  }
+  else{
+    let multiplier = ((max - min) / (range.1 as f64 - range.0 as f64)) as f32;
+    (multiplier, (min - multiplier as f64 * range.0 as f64) as f32)
+  };
+  let data = values.iter().map(|value|{
+    if value.is_finite(){
+      // Clamp errors of rounding into real.
+      ((value - offset as f64) / multiplier as f64).round().max(range.0 as f64).min(range.1 as f64) as i32
+    }
+    else{
+      qnull_base::I
+    }
+  }).collect::<Vec<i32>>();
+  let (data_min, data_max) = data.iter().filter(|num| **num != qnull_base::I).fold((i32::MAX, i32::MIN), |(min, max), num| (min.min(*num), max.max(*num)));
+  if data_min > data_max{
+    // No data
+    (multiplier, offset, data, 0, 0)
+  }
+  else{
+    (multiplier, offset, data, data_min, data_max)
+  }
+}
+
+/// Convert analog columns of a data table deserialized with `config` into a given file type in place. Data are kept as
+///  they are if they fit in the range of the file type. Otherwise data are restored into engineering unit and quantised
+///  with new factors. Returns a copy of the configuration whose `file_type`, factors and limits are updated.
+pub(crate) fn convert_comtrade_data(table: K, config: K, file_type: &str) -> Result<K, &'static str>{
+  let spec = DataSpec::from_config(config)?;
+  let target = DataFileType::from_symbol(file_type)?;
+  let columns = get_data_columns(table, spec.file_type.analog_qtype(), &spec)?;
+  let num_analog_channel = spec.num_analog_channel as usize;
+  let multipliers = get_config_list::<E>(config, "channel_multiplier", qtype::REAL_LIST, num_analog_channel, "invalid channel multiplier\0")?;
+  let offsets = get_config_list::<E>(config, "channel_offset_adder", qtype::REAL_LIST, num_analog_channel, "invalid channel offset adder\0")?;
+  let minimums = get_config_list::<I>(config, "minimum_value", qtype::INT_LIST, num_analog_channel, "invalid minimum value\0")?;
+  let maximums = get_config_list::<I>(config, "maximum_value", qtype::INT_LIST, num_analog_channel, "invalid maximum value\0")?;
+
+  let new_multipliers = new_list(qtype::REAL_LIST, num_analog_channel as J);
+  let new_offsets = new_list(qtype::REAL_LIST, num_analog_channel as J);
+  let new_minimums = new_list(qtype::INT_LIST, num_analog_channel as J);
+  let new_maximums = new_list(qtype::INT_LIST, num_analog_channel as J);
+  for i in 0..num_analog_channel{
+    let (mut multiplier, mut offset, mut minimum, mut maximum) = (multipliers[i], offsets[i], minimums[i], maximums[i]);
+    let column = columns[2+i];
+    let converted = match (spec.file_type, target){
+      (DataFileType::Float32, DataFileType::Float32) => None,
+      (_, DataFileType::Float32) => {
+        // Integers are held as they are.
+        let converted = new_list(qtype::REAL_LIST, column.len());
+        converted.as_mut_slice::<E>().iter_mut().zip(column.as_mut_slice::<I>().iter()).for_each(|(real, int)|{
+          *real = if *int == qnull_base::I {qnull_base::E} else {*int as f32};
+        });
+        Some(converted)
+      },
+      _ => {
+        let range = target.analog_range();
+        let in_range = spec.file_type != DataFileType::Float32 && column.as_mut_slice::<I>().iter().all(|num| *num == qnull_base::I || (range.0..=range.1).contains(num));
+        if in_range{
+          // Limits are narrowed into the range of the file type.
+          minimum = minimum.max(range.0).min(range.1);
+          maximum = maximum.max(range.0).min(range.1);
+          None
+        }
+        else{
+          let values = if spec.file_type == DataFileType::Float32{
+            column.as_mut_slice::<E>().iter().map(|real| multiplier as f64 * *real as f64 + offset as f64).collect::<Vec<f64>>()
           }
-        },
-        Err(error) => {
-          decrement_reference_count(keys);
-          decrement_reference_count(values);
+          else{
+            column.as_mut_slice::<I>().iter().map(|int| if *int == qnull_base::I {qnull_base::F} else {multiplier as f64 * *int as f64 + offset as f64}).collect::<Vec<f64>>()
+          };
+          let (multiplier_, offset_, data, minimum_, maximum_) = quantise_analog_column(&values, range);
+          multiplier = multiplier_;
+          offset = offset_;
+          minimum = minimum_;
+          maximum = maximum_;
+          let converted = new_list(qtype::INT_LIST, data.len() as J);
+          converted.as_mut_slice::<I>().copy_from_slice(&data);
+          Some(converted)
+        }
+      }
+    };
+    if let Some(converted) = converted{
+      decrement_reference_count(column);
+      columns[2+i] = converted;
+    }
+    new_multipliers.as_mut_slice::<E>()[i] = multiplier;
+    new_offsets.as_mut_slice::<E>()[i] = offset;
+    new_minimums.as_mut_slice::<I>()[i] = minimum;
+    new_maximums.as_mut_slice::<I>()[i] = maximum;
+  }
+
+  update_dictionary(config, vec![
+    ("file_type", new_symbol(file_type)),
+    ("channel_multiplier", new_multipliers),
+    ("channel_offset_adder", new_offsets),
+    ("minimum_value", new_minimums),
+    ("maximum_value", new_maximums)
+  ])
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Interface                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Deserialize the data file (`.dat`) of COMTRADE format into q table.
+/// # Parameters
+/// - `data`: 
+///   - symbol: File path which starts with `:`. Lines can be delimited by <CR/LF>, <LF> or <CR>.
+///   - string: File contents.
+///   - list of byte: File contents
+/// - `num_analog_channel_`: The number of analog channels.
+/// - `num_status_channel_`: The number of status channels.
+/// - `critical_timestamp_`: Flag of whether timestamp is critical or not. Blank timestamps are filled as null if
+///   timestamp is not critical.
+/// - `first_data_time_`: Timestamp of the first data.
+/// - `timestamp_multiplication_factor_`: Multiplication factor for timestamp in each record. Timestamp of each record is
+///   `first_data_time` + timestamp * `timestamp_multiplication_factor_`.
+/// - `file_type_`: File type of the data: `ascii`, `binary`, `binary32` or `float32`. Analog data of `float32` is
+///   deserialized into real columns. A bool flag of whether data is encoded in ASCII (`1b`) or binary (`0b`) is also
+///   accepted for compatibility.
+#[no_mangle]
+pub extern "C" fn deserialize_comtrade_data(data: K, num_analog_channel_: K, num_status_channel_: K, critical_timestamp_: K, first_data_time_: K, timestamp_multiplication_factor_: K, file_type_: K) -> K{
+  catch_panic(||{
+    let spec = match DataSpec::from_arguments(num_analog_channel_, num_status_channel_, critical_timestamp_, first_data_time_, timestamp_multiplication_factor_, file_type_){
+      Ok(spec) => spec,
+      Err(error) => return new_error(error)
+    };
+
+    deserialize_comtrade_data_from(data, &spec)
+  })
+}
+
+/// Deserialize the data file (`.dat`) of COMTRADE format into q table with a deserialized configuration and options.
+/// # Parameters
+/// - `data`: 
+///   - symbol: File path which starts with `:`. Lines can be delimited by <CR/LF>, <LF> or <CR>.
+///   - string: File contents.
+///   - list of byte: File contents
+/// - `config`: Configuration deserialized by `deserialize_comtrade_config`. If timestamps are non-critical, time of a
+///   record whose timestamp is blank is derived from its sample number and `sample_rates`/`last_sample_number`.
+/// - `options`: Dictionary of options whose values are a compound list. Missing options take default values.
+///   - `engineering_unit` (bool): Convert analog data into engineering unit with `channel_multiplier` and
+///     `channel_offset_adder`. Analog columns become float. Default: `0b`.
+///   - `side` (symbol): Refer analog data in engineering unit to `primary or `secondary side regardless of
+///     `scaling_identifier`. Implies `engineering_unit`. Default: as stored.
+///   - `column_name` (symbol): `index names columns `analog_channel_N` and `status_channel_N`. `channel_id names columns
+///     after `analog_channel_id` and `status_channel_id` sanitised into valid q names. Default: `index.
+///   - `skew` (symbol): `none ignores `skew` of analog channels. `time_column adds a column `time_<analog column name>`
+///     corrected by the skew for each analog channel. `interpolate resamples analog data onto the common sample instants
+///     by linear interpolation and analog columns become float. Default: `none.
+///   - `analog_channels` (list of int, long or symbol): Analog channels to load given by `analog_channel_index` or
+///     `analog_channel_id`. Columns appear in the given order and other channels are skipped. Default: all channels.
+///   - `status_channels` (list of int, long or symbol): Status channels to load given by `status_channel_index` or
+///     `status_channel_id`. Default: all channels.
+///   - `sample_range` (list of int or long): Pair of the first and the last sample numbers of records to load. A null
+///     bound leaves the range open. Default: all records.
+///   - `time_range` (list of timestamp): Pair of the first and the last time of records to load. A null bound leaves the
+///     range open. Cannot be combined with `sample_range`. Default: all records.
+///   - `threads` (int or long): The number of threads to decode data written in ASCII format. Lines are split into
+///     blocks decoded in parallel and q lists are built on the main thread. Capped by the available parallelism. Default: 1.
+#[no_mangle]
+pub extern "C" fn deserialize_comtrade_data_with_config(data: K, config: K, options: K) -> K{
+  catch_panic(||{
+    let mut spec = match DataSpec::from_config(config){
+      Ok(spec) => spec,
+      Err(error) => return new_error(error)
+    };
+
+    let side = match find_dictionary_value(options, "side"){
+      Ok(side) => side,
+      Err(error) => return new_error(error)
+    };
+    match get_bool_option(options, "engineering_unit", false){
+      Ok(engineering_unit) if engineering_unit || side.is_some() => {
+        if let Err(error) = spec.set_engineering_unit(config){
           return new_error(error);
         }
+      },
+      Ok(_) => (),
+      Err(error) => return new_error(error)
+    }
+    if let Some(side) = side{
+      if let Err(error) = side.get_symbol().and_then(|side| spec.set_side(config, side)){
+        return new_error(error);
       }
     }
+    let column_name = match get_symbol_option(options, "column_name", "index"){
+      Ok(column_name) => column_name,
+      Err(error) => return new_error(error)
+    };
+    match column_name.as_str(){
+      "index" => (),
+      "channel_id" => {
+        if let Err(error) = spec.set_channel_id_column_names(config){
+          return new_error(error);
+        }
+      },
+      _ => return new_error("invalid column name - expected `index or `channel_id\0")
+    }
+    if let Err(error) = get_symbol_option(options, "skew", "none").and_then(|mode| spec.set_skew_correction(config, mode.as_str())){
+      return new_error(error);
+    }
+    let projection = find_dictionary_value(options, "analog_channels").and_then(|analog|{
+      find_dictionary_value(options, "status_channels").and_then(|status| spec.set_channel_projection(config, analog, status))
+    });
+    if let Err(error) = projection{
+      return new_error(error);
+    }
+    let record_range = find_dictionary_value(options, "sample_range").and_then(|sample_range|{
+      find_dictionary_value(options, "time_range").and_then(|time_range| spec.set_record_range(sample_range, time_range))
+    });
+    if let Err(error) = record_range{
+      return new_error(error);
+    }
+    if let Err(error) = get_long_option(options, "threads", 1).and_then(|threads| spec.set_threads(threads)){
+      return new_error(error);
+    }
 
-    flip(new_dictionary(keys, values))
-  }
+    deserialize_comtrade_data_from(data, &spec)
+  })
+}
 
+/// Deserialize the data file (`.dat`) of COMTRADE format in chunks and pass each partial table to a q function so that
+///  a recording which does not fit in memory can be processed.
+/// # Parameters
+/// - `path`: File path which starts with `:`. The file is mapped into memory and decoded chunk by chunk.
+/// - `config`: Configuration deserialized by `deserialize_comtrade_config`.
+/// - `chunk_size`: The number of records of each partial table (int or long).
+/// - `function`: q function called with each partial table in the form of `deserialize_comtrade_data`. Its result is
+///   discarded and an error raised by the function stops the deserialization.
+/// # Return
+/// The number of records.
+#[no_mangle]
+pub extern "C" fn deserialize_comtrade_data_in_chunks(path: K, config: K, chunk_size: K, function: K) -> K{
+  catch_panic(||{
+    let spec = match DataSpec::from_config(config){
+      Ok(spec) => spec,
+      Err(error) => return new_error(error)
+    };
+    let chunk_size = match chunk_size.get_type(){
+      qtype::INT_ATOM => chunk_size.get_int().map(|size| size as J),
+      _ => chunk_size.get_long()
+    };
+    let chunk_size = match chunk_size{
+      Ok(size) if size > 0 => size as usize,
+      _ => return new_error("invalid chunk size - expected positive int or long\0")
+    };
+
+    let mut contents_buffer = ContentsBuffer::default();
+    let result = if spec.file_type == DataFileType::Ascii{
+      match load_ascii_data(path, &mut contents_buffer){
+        Ok(string) => deserialize_comtrade_data_ascii_in_chunks(string, chunk_size, function, path, &spec),
+        Err(error) => return new_error(error)
+      }
+    }
+    else{
+      match load_binary_data(path, &mut contents_buffer){
+        Ok(bytes) => deserialize_comtrade_data_binary_in_chunks(bytes, chunk_size, function, path, &spec),
+        Err(error) => return new_error(error)
+      }
+    };
+
+    match result{
+      Ok(num_records) => new_long(num_records),
+      Err(error) => error
+    }
+  })
+}
+
+/// Serialize a q table into the data file (`.dat`) of COMTRADE format.
+/// # Parameters
+/// - `table`: Table in the form of `deserialize_comtrade_data`, i.e., sample number (int), time (timestamp), analog channels
+///   and status channels (bool). Columns are identified by position. Analog columns must be real for `float32` and int
+///   otherwise.
+/// - `config`: Configuration deserialized by `deserialize_comtrade_config`. `file_type` decides the format.
+/// - `path`: File path which starts with `:`.
+#[no_mangle]
+pub extern "C" fn serialize_comtrade_data(table: K, config: K, path: K) -> K{
+  catch_panic(||{
+    match DataSpec::from_config(config).and_then(|spec| serialize_comtrade_data_contents(table, &spec)).and_then(|bytes| write_data(path, &bytes)){
+      Ok(_) => increment_reference_count(path),
+      Err(error) => new_error(error)
+    }
+  })
 }
 
+/// Serialize a q table of analog data in engineering unit into the data file (`.dat`) of COMTRADE format. Multiplier and
+///  offset of each analog channel are chosen to maximise the resolution within the range of the file type and data are
+///  quantised accordingly. Data of `float32` is written as it is with the multiplier 1 and the offset 0.
+/// # Parameters
+/// - `table`: Table of sample number (int), time (timestamp), analog channels (float) and status channels (bool).
+///   Columns are identified by position.
+/// - `config`: Configuration deserialized by `deserialize_comtrade_config`. `file_type` decides the format.
+/// - `path`: File path which starts with `:`.
+/// # Return
+/// Configuration whose `channel_multiplier`, `channel_offset_adder`, `minimum_value` and `maximum_value` are updated.
+#[no_mangle]
+pub extern "C" fn serialize_comtrade_scaled_data(table: K, config: K, path: K) -> K{
+  catch_panic(||{
+    let spec = match DataSpec::from_config(config){
+      Ok(spec) => spec,
+      Err(error) => return new_error(error)
+    };
+    let columns = match get_data_columns(table, qtype::FLOAT_LIST, &spec){
+      Ok(columns) => columns,
+      Err(error) => return new_error(error)
+    };
+    let num_analog_channel = spec.num_analog_channel as usize;
+    let multipliers = new_list(qtype::REAL_LIST, num_analog_channel as J);
+    let offsets = new_list(qtype::REAL_LIST, num_analog_channel as J);
+    let minimums = new_list(qtype::INT_LIST, num_analog_channel as J);
+    let maximums = new_list(qtype::INT_LIST, num_analog_channel as J);
+    let mut scaled_columns = columns.to_vec();
+    for i in 0..num_analog_channel{
+      let values = columns[2+i].as_mut_slice::<F>();
+      let scaled = if spec.file_type == DataFileType::Float32{
+        let data = values.iter().map(|value| *value as f32).collect::<Vec<f32>>();
+        let (minimum, maximum) = data.iter().filter(|value| value.is_finite()).fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| (min.min(*value), max.max(*value)));
+        // Limits are integers in the configuration.
+        let (minimum, maximum) = if minimum > maximum {(0, 0)} else {(minimum.floor().max(-i32::MAX as f32) as i32, maximum.ceil().min(i32::MAX as f32) as i32)};
+        minimums.as_mut_slice::<I>()[i] = minimum;
+        maximums.as_mut_slice::<I>()[i] = maximum;
+        multipliers.as_mut_slice::<E>()[i] = 1.0;
+        offsets.as_mut_slice::<E>()[i] = 0.0;
+        let scaled = new_list(qtype::REAL_LIST, data.len() as J);
+        scaled.as_mut_slice::<E>().copy_from_slice(&data);
+        scaled
+      }
+      else{
+        let (multiplier, offset, data, minimum, maximum) = quantise_analog_column(values, spec.file_type.analog_range());
+        multipliers.as_mut_slice::<E>()[i] = multiplier;
+        offsets.as_mut_slice::<E>()[i] = offset;
+        minimums.as_mut_slice::<I>()[i] = minimum;
+        maximums.as_mut_slice::<I>()[i] = maximum;
+        let scaled = new_list(qtype::INT_LIST, data.len() as J);
+        scaled.as_mut_slice::<I>().copy_from_slice(&data);
+        scaled
+      };
+      scaled_columns[2+i] = scaled;
+    }
+
+    let result = serialize_comtrade_data_columns(&scaled_columns, &spec).and_then(|bytes| write_data(path, &bytes));
+    for column in scaled_columns[2..2+num_analog_channel].iter(){
+      decrement_reference_count(*column);
+    }
+    match result{
+      Ok(_) => match update_dictionary(config, vec![
+        ("channel_multiplier", multipliers),
+        ("channel_offset_adder", offsets),
+        ("minimum_value", minimums),
+        ("maximum_value", maximums)
+      ]){
+        Ok(updated) => updated,
+        Err(error) => new_error(error)
+      },
+      Err(error) => {
+        decrement_reference_count(multipliers);
+        decrement_reference_count(offsets);
+        decrement_reference_count(minimums);
+        decrement_reference_count(maximums);
+        new_error(error)
+      }
+    }
+  })
+}
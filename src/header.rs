@@ -0,0 +1,100 @@
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                            Load Libraries                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+use std::collections::HashSet;
+use kdbplus::*;
+use kdbplus::api::*;
+use super::{ContentsBuffer, load_binary_data, Lines, catch_panic};
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Global Variables                            //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Maximum length of a key of a line regarded as a setting.
+const MAX_SETTING_KEY_LENGTH: usize = 64;
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Private Fucntions                           //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Split a line of the header file (`.hdr`) into a key and a value if it looks like a setting.
+/// Format: "key=value" or "key: value". A line without '=' whose ':' is between digits (e.g. "17:38:26") is regarded
+///  as a time rather than a setting.
+fn deserialize_comtrade_header_setting(line: &str) -> Option<(String, &str)>{
+  let (key, value) = match line.split_once('='){
+    Some((key, value)) => (key, value),
+    None => {
+      let (key, value) = line.split_once(':')?;
+      if key.ends_with(|c: char| c.is_ascii_digit()) && value.starts_with(|c: char| c.is_ascii_digit()){
+        // Time
+        return None;
+      }
+      (key, value)
+    }
+  };
+  let key = key.trim();
+  if key.is_empty() || key.len() > MAX_SETTING_KEY_LENGTH{
+    None
+  }
+  else{
+    Some((key.split_whitespace().collect::<Vec<&str>>().join("_"), value.trim()))
+  }
+}
+
+/// Deserialize contents of the header file (`.hdr`) of COMTRADE format into q dictionary. The text is copied byte for
+///  byte while settings are parsed over a copy decoded as UTF-8 lossily.
+pub(crate) fn deserialize_comtrade_header_contents(bytes: &[u8]) -> K{
+  let string = String::from_utf8_lossy(bytes);
+  let mut setting_keys = new_list(qtype::SYMBOL_LIST, 0);
+  let mut setting_values = new_list(qtype::COMPOUND_LIST, 0);
+  // A key which appears again is de-duplicated by appending `_2`, `_3`, ...
+  let mut used = HashSet::new();
+  Lines::new(&string).filter_map(deserialize_comtrade_header_setting).for_each(|(key, value)|{
+    let mut unique = key.clone();
+    let mut suffix = 2;
+    while used.contains(&unique){
+      unique = format!("{}_{}", key, suffix);
+      suffix += 1;
+    }
+    // Can be reallocated after push
+    setting_keys = setting_keys.push_symbol(unique.as_str()).unwrap();
+    used.insert(unique);
+    setting_values = setting_values.push(new_string(value)).unwrap();
+  });
+
+  let keys = new_list(qtype::SYMBOL_LIST, 2);
+  let keys_slice = keys.as_mut_slice::<S>();
+  keys_slice[0]=internalize(str_to_S!("text"));
+  keys_slice[1]=internalize(str_to_S!("settings"));
+  let text = new_list(qtype::STRING, bytes.len() as J);
+  text.as_mut_slice::<G>().copy_from_slice(bytes);
+  let values = new_list(qtype::COMPOUND_LIST, 2);
+  values.as_mut_slice::<K>().copy_from_slice(&[text, new_dictionary(setting_keys, setting_values)]);
+  new_dictionary(keys, values)
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Interface                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Deserialize the header file (`.hdr`) of COMTRADE format into q dictionary with keys `text` and `settings`.
+///  `text` is the raw text of the file copied byte for byte and `settings` is a dictionary of lines which look like
+///  "key: value" or "key=value". A key which appears again is suffixed with `_2`, `_3`, ...
+///  Bytes of settings which are not valid UTF-8 are replaced with U+FFFD.
+/// # Parameters
+/// - `data`: 
+///   - symbol: File path which starts with `:`.
+///   - string: File contents.
+///   - list of byte: File contents
+#[no_mangle]
+pub extern "C" fn deserialize_comtrade_header(data: K) -> K{
+  catch_panic(||{
+    let bytes;
+    let mut contents_buffer = ContentsBuffer::default();
+    // Load data into bytes.
+    set_bytes!(data, bytes, contents_buffer);
+
+    deserialize_comtrade_header_contents(bytes)
+  })
+}
@@ -4,7 +4,7 @@
 
 use kdbplus::*;
 use kdbplus::api::*;
-use super::load_ascii_data;
+use super::{ContentsBuffer, load_ascii_data, write_data, split_lines, catch_panic, ComtradeError};
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                          Private Fucntions                           //
@@ -44,24 +44,10 @@ fn deserialize_comtrade_info_entry(line: &str, mut entry_keys: K, mut entry_valu
   }
 }
 
-//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
-//                               Interface                              //
-//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
-
-/// Deserialize the information file (`.inf`) of COMTRADE format into q dictionary.
-/// # Parameters
-/// - `data`: 
-///   - symbol: File path which starts with `:`. Thsi file must be delimited <CR/LF>. i.e., the file must be in the Windows format.
-///   - string: File contents.
-#[no_mangle]
-pub extern "C" fn deserialize_comtrade_info(data: K) -> K{
-  let string;
-  let mut contents_buffer = String::new();
-  // Load data into string.
-  set_string!(data, string, contents_buffer);
-
-  // inf fle can include empty lines
-  let lines= string.split_terminator("\r\n").filter(|line| *line != "").collect::<Vec<&str>>();
+/// Deserialize contents of the information file (`.inf`) of COMTRADE format into q dictionary.
+pub(crate) fn deserialize_comtrade_info_contents(string: &str) -> Result<K, ComtradeError>{
+  // inf fle can include empty lines. Line numbers are kept to locate an error.
+  let (line_numbers, lines): (Vec<usize>, Vec<&str>) = split_lines(string).into_iter().enumerate().filter(|(_, line)| !line.is_empty()).map(|(idx, line)| (idx + 1, line)).unzip();
   let mut keys = new_list(qtype::SYMBOL_LIST, 0);
   let mut values = new_list(qtype::COMPOUND_LIST, 0);
   let mut cursor = 0;
@@ -83,7 +69,7 @@ pub extern "C" fn deserialize_comtrade_info(data: K) -> K{
                 cursor+=1;
               },
               Err(error) => {
-                return new_error(error);
+                return Err(ComtradeError::from(error).at_line(line_numbers[cursor]).with_token(lines[cursor]));
               }
             }
           }
@@ -96,10 +82,140 @@ pub extern "C" fn deserialize_comtrade_info(data: K) -> K{
         }
       },
       Err(error) => {
-        return new_error(error);
+        return Err(ComtradeError::from(error).at_line(line_numbers[cursor]).with_token(lines[cursor]));
       }
     }
   }
 
-  new_dictionary(keys, values)
+  Ok(new_dictionary(keys, values))
+}
+
+/// Serialize values of an entry of the information file (.inf) of COMTRADE format. Values are joined with ','.
+/// A value can be a list of strings, a string, a char, a symbol or a list of symbols.
+fn serialize_comtrade_info_entry_values(value: K) -> Result<String, &'static str>{
+  let values = match value.get_type(){
+    qtype::COMPOUND_LIST => value.as_mut_slice::<K>().iter().map(|element| match element.get_type(){
+      qtype::STRING => element.get_str().map(|string| string.to_string()),
+      qtype::CHAR => element.get_char().map(|character| character.to_string()),
+      _ => Err("invalid entry value - expected list of strings\0")
+    }).collect::<Result<Vec<String>, &'static str>>()?,
+    qtype::STRING => vec![value.get_str()?.to_string()],
+    qtype::CHAR => vec![value.get_char()?.to_string()],
+    qtype::SYMBOL_ATOM => vec![value.get_symbol()?.to_string()],
+    qtype::SYMBOL_LIST => value.as_mut_slice::<S>().iter().map(|symbol| S_to_str(*symbol).to_string()).collect(),
+    _ => return Err("invalid entry value - expected list of strings\0")
+  };
+  let values = values.join(",");
+  if values.contains(['\r', '\n']){
+    Err("invalid entry value - line break in value\0")
+  }
+  else{
+    Ok(values)
+  }
+}
+
+/// Serialize a section of the information file (.inf) of COMTRADE format. `[Public <section>]` is used as a header
+///  unless the section name has its own header word separated by a space, e.g. "Company1 event_rec".
+fn serialize_comtrade_info_section(section: &str, entry_keys: &[S], entry_values: &[K], lines: &mut Vec<String>) -> Result<(), &'static str>{
+  if section.is_empty() || section.contains(['[', ']', '\r', '\n']){
+    return Err("invalid section name\0");
+  }
+  if section.contains(' '){
+    lines.push(format!("[{}]", section));
+  }
+  else{
+    lines.push(format!("[Public {}]", section));
+  }
+  for (key, value) in entry_keys.iter().zip(entry_values.iter()){
+    let key = S_to_str(*key);
+    if key.is_empty() || key.contains(['=', '\r', '\n']){
+      return Err("invalid entry key\0");
+    }
+    lines.push(format!("{}={}", key, serialize_comtrade_info_entry_values(*value)?));
+  }
+  // Sections are separated by an empty line.
+  lines.push(String::new());
+  Ok(())
+}
+
+/// Serialize a dictionary in the form of `deserialize_comtrade_info` into contents of the information file (`.inf`) of
+///  COMTRADE format. Order of sections and entries is preserved. Lines are delimited by <CR/LF>.
+pub(crate) fn serialize_comtrade_info_contents(info: K) -> Result<String, &'static str>{
+  if info.get_type() != qtype::DICTIONARY{
+    return Err("not a dictionary\0");
+  }
+  let info_slice = info.as_mut_slice::<K>();
+  if info_slice[0].get_type() != qtype::SYMBOL_LIST{
+    return Err("invalid section name\0");
+  }
+  let sections = info_slice[0].as_mut_slice::<S>();
+  let mut lines = Vec::new();
+  match info_slice[1].get_type(){
+    qtype::COMPOUND_LIST => {
+      for (section, entries) in sections.iter().zip(info_slice[1].as_mut_slice::<K>().iter()){
+        if entries.get_type() != qtype::DICTIONARY{
+          return Err("invalid section - expected dictionary\0");
+        }
+        let entries_slice = entries.as_mut_slice::<K>();
+        if entries_slice[0].get_type() != qtype::SYMBOL_LIST || entries_slice[1].get_type() != qtype::COMPOUND_LIST{
+          return Err("invalid section - expected dictionary of symbol keys and list of values\0");
+        }
+        serialize_comtrade_info_section(S_to_str(*section), entries_slice[0].as_mut_slice::<S>(), entries_slice[1].as_mut_slice::<K>(), &mut lines)?;
+      }
+    },
+    qtype::TABLE => {
+      // Sections sharing the same entry keys are collapsed into a table.
+      let columns = info_slice[1].get_dictionary()?.as_mut_slice::<K>();
+      let entry_keys = columns[0].as_mut_slice::<S>();
+      let columns = columns[1].as_mut_slice::<K>();
+      if columns.iter().any(|column| column.get_type() != qtype::COMPOUND_LIST){
+        return Err("invalid section - expected dictionary of symbol keys and list of values\0");
+      }
+      for (row, section) in sections.iter().enumerate(){
+        let entry_values = columns.iter().map(|column| column.as_mut_slice::<K>()[row]).collect::<Vec<K>>();
+        serialize_comtrade_info_section(S_to_str(*section), entry_keys, &entry_values, &mut lines)?;
+      }
+    },
+    _ => return Err("invalid section - expected dictionary\0")
+  }
+  Ok(lines.join("\r\n"))
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                               Interface                              //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Deserialize the information file (`.inf`) of COMTRADE format into q dictionary.
+/// # Parameters
+/// - `data`: 
+///   - symbol: File path which starts with `:`. Lines can be delimited by <CR/LF>, <LF> or <CR>.
+///   - string: File contents.
+#[no_mangle]
+pub extern "C" fn deserialize_comtrade_info(data: K) -> K{
+  catch_panic(||{
+    let string;
+    let mut contents_buffer = ContentsBuffer::default();
+    // Load data into string.
+    set_string!(data, string, contents_buffer);
+
+    match deserialize_comtrade_info_contents(string){
+      Ok(info) => info,
+      Err(error) => error.in_source(data).to_q_error()
+    }
+  })
+}
+
+/// Serialize a dictionary into the information file (`.inf`) of COMTRADE format.
+/// # Parameters
+/// - `info`: Dictionary of sections in the form of `deserialize_comtrade_info`. Each section is written with a header
+///   `[Public <section>]` unless the section name has its own header word, e.g. `$"Company1 event_rec"`.
+/// - `path`: File path which starts with `:`.
+#[no_mangle]
+pub extern "C" fn serialize_comtrade_info(info: K, path: K) -> K{
+  catch_panic(||{
+    match serialize_comtrade_info_contents(info).and_then(|contents| write_data(path, contents.as_bytes())){
+      Ok(_) => increment_reference_count(path),
+      Err(error) => new_error(error)
+    }
+  })
 }
@@ -2,8 +2,12 @@
 //                            Load Libraries                            //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+use std::cell::UnsafeCell;
 use std::fs::OpenOptions;
-use std::io::{Read, BufReader};
+use std::io::{Write, BufWriter};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use memmap2::Mmap;
+use kdbplus::qtype;
 use kdbplus::api::*;
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
@@ -13,12 +17,21 @@ use kdbplus::api::*;
 /// Offset between Unix epoch and q epoch in nanosecond.
 const KDB_TIMESTAMP_OFFSET: i64 = 946684800000000000;
 
+/// Maximum length in bytes of a message of q error made at runtime. A longer message is truncated.
+const MAX_RUNTIME_ERROR_LENGTH: usize = 255;
+
+thread_local!{
+  /// Message of the last q error made at runtime on this thread terminated by null. q reads the message after an
+  ///  interface function returns and the next error overwrites it, so that messages do not pile up in the symbol pool.
+  static RUNTIME_ERROR_MESSAGE: UnsafeCell<[u8; MAX_RUNTIME_ERROR_LENGTH + 1]> = UnsafeCell::new([0; MAX_RUNTIME_ERROR_LENGTH + 1]);
+}
+
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 //                               Macros                                 //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
 /// Parse [token_idx]th value in a vector of tokens into int or real. The parsed value is pushed
-///  to the given q list object which is a [slice_idx]th element of a root list.
+///  to the given q list object which is a [slice_idx]th element of a root list. An error carries the position and the token.
 #[macro_export(crate)]
 macro_rules! parse_token {
   [i32; $tokens:expr, $token_idx:expr, $slice:expr, $slice_idx:expr, $error:expr] => {
@@ -26,7 +39,7 @@ macro_rules! parse_token {
       Ok(num) => {
         $slice[$slice_idx].push_raw(num).unwrap();
       },
-      Err(_) => return Err($error)
+      Err(_) => return Err($crate::ComtradeError::from($error).at_field($token_idx, $tokens[$token_idx]))
     }
   };
 
@@ -35,7 +48,7 @@ macro_rules! parse_token {
       Ok(num) => {
         $slice[$slice_idx].push_raw(num as f32).unwrap();
       },
-      Err(_) => return Err($error)
+      Err(_) => return Err($crate::ComtradeError::from($error).at_field($token_idx, $tokens[$token_idx]))
     }
   };
 }
@@ -45,11 +58,13 @@ macro_rules! parse_token {
 macro_rules! set_string {
   ($data: expr, $string: expr, $contents_buffer: expr) => {
     if $data.get_type() == qtype::SYMBOL_ATOM{
-      if let Err(error) = load_ascii_data($data, &mut $contents_buffer){
-        return new_error(error);
-      }
-      else{
-        $string=$contents_buffer.as_str();
+      match load_ascii_data($data, &mut $contents_buffer){
+        Ok(string_) => {
+          $string = string_;
+        },
+        Err(error) => {
+          return new_error(error)
+        }
       }
     }
     else{
@@ -70,55 +85,399 @@ macro_rules! set_string {
 macro_rules! set_bytes {
   ($data: expr, $bytes: expr, $contents_buffer: expr) => {
     if $data.get_type() == qtype::SYMBOL_ATOM{
-      if let Err(error) = load_binary_data($data, &mut $contents_buffer){
-        return new_error(error);
-      }
-      else{
-        $bytes=$contents_buffer.as_mut_slice();
+      match load_binary_data($data, &mut $contents_buffer){
+        Ok(bytes_) => {
+          $bytes = bytes_;
+        },
+        Err(error) => {
+          return new_error(error)
+        }
       }
     }
     else{
-      $bytes=$data.as_mut_slice::<G>();
+      match $data.get_type(){
+        qtype::BYTE_LIST | qtype::STRING => {
+          $bytes=$data.as_mut_slice::<G>();
+        },
+        _ => {
+          return new_error("expected symbol, string or list of byte\0")
+        }
+      }
     }
   };
 }
 
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
-//                          Private Fucntions                           //
+//                               Struct                                 //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
-/// Read ASCII file into `String` buffer.
-fn load_ascii_data(data: K, contents_buffer: &mut String) -> Result<(), &'static str>{
-  // File path
-  if let Some(path) = data.get_symbol().unwrap().strip_prefix(':'){
-    if let Ok(file) = OpenOptions::new().read(true).write(false).create(false).open(path){
-      let mut reader=BufReader::new(file);
-      reader.read_to_string(contents_buffer).unwrap();
-      Ok(())
+/// Buffer holding contents of a file loaded by `set_string` or `set_bytes`.
+#[derive(Default)]
+struct ContentsBuffer{
+  /// File mapped into memory.
+  mapped: Option<Mmap>
+}
+
+/// Location of an error. Locations which are not known are omitted from the message of q error.
+#[derive(Clone, Debug, Default)]
+struct ErrorLocation{
+  /// File path or section of the combined file.
+  file: Option<String>,
+  /// Component of the configuration file.
+  component: Option<usize>,
+  /// Line number (1-based) of a text file.
+  line: Option<usize>,
+  /// Record number (1-based) of a binary data file.
+  record: Option<usize>,
+  /// Field index (1-based) in a line.
+  field: Option<usize>,
+  /// Offending token.
+  token: Option<String>
+}
+
+/// Error of deserialization with the location where it occurred. The location is boxed to keep `Result` small.
+#[derive(Clone, Debug)]
+pub(crate) struct ComtradeError{
+  /// Description of the error terminated by null.
+  message: &'static str,
+  /// Where the error occurred.
+  location: Box<ErrorLocation>
+}
+
+impl ComtradeError{
+  /// Set the file path or section unless it is already set.
+  fn in_file(mut self, file: &str) -> Self{
+    self.location.file.get_or_insert_with(|| file.to_string());
+    self
+  }
+
+  /// Set the file path given to an interface function unless it is already set. Nothing is set for file contents.
+  fn in_source(self, source: K) -> Self{
+    match source.get_type(){
+      qtype::SYMBOL_ATOM => match source.get_symbol(){
+        Ok(path) => self.in_file(path.trim_start_matches(':')),
+        Err(_) => self
+      },
+      _ => self
+    }
+  }
+
+  /// Set the component of the configuration file unless it is already set.
+  fn in_component(mut self, component: usize) -> Self{
+    self.location.component.get_or_insert(component);
+    self
+  }
+
+  /// Set the line number (1-based) unless it is already set.
+  fn at_line(mut self, line: usize) -> Self{
+    self.location.line.get_or_insert(line);
+    self
+  }
+
+  /// Set the record number (1-based) unless it is already set.
+  fn at_record(mut self, record: usize) -> Self{
+    self.location.record.get_or_insert(record);
+    self
+  }
+
+  /// Set the field index (0-based position in a line) and the offending token.
+  fn at_field(mut self, field: usize, token: &str) -> Self{
+    self.location.field = Some(field + 1);
+    self.location.token = Some(token.to_string());
+    self
+  }
+
+  /// Set the offending token, e.g., a whole line.
+  fn with_token(mut self, token: &str) -> Self{
+    self.location.token = Some(token.to_string());
+    self
+  }
+
+  /// Build a q error. The location is appended to the description, e.g.,
+  ///  "invalid analog channel data - file data.dat, line 5, field 4, token \"1O\"".
+  fn to_q_error(&self) -> K{
+    let mut location = Vec::new();
+    if let Some(file) = &self.location.file{
+      location.push(format!("file {}", file));
+    }
+    if let Some(component) = self.location.component{
+      location.push(format!("component {}", component));
+    }
+    if let Some(line) = self.location.line{
+      location.push(format!("line {}", line));
+    }
+    if let Some(record) = self.location.record{
+      location.push(format!("record {}", record));
+    }
+    if let Some(field) = self.location.field{
+      location.push(format!("field {}", field));
+    }
+    if let Some(token) = &self.location.token{
+      location.push(format!("token {:?}", token));
+    }
+    if location.is_empty(){
+      new_error(self.message)
     }
     else{
-      return Err("no such file\0");
+      new_runtime_error(&format!("{} - {}", self.message.trim_end_matches('\0'), location.join(", ")))
     }
   }
-  else{
-    return Err("invalid file name - missing ':'\0");
+}
+
+impl From<&'static str> for ComtradeError{
+  fn from(message: &'static str) -> Self{
+    ComtradeError{message, location: Box::default()}
+  }
+}
+
+/// Iterator over lines of text delimited by <CR/LF>, <LF> or <CR>. The last line does not need to be terminated.
+struct Lines<'a>{
+  string: &'a str,
+  cursor: usize
+}
+
+impl<'a> Lines<'a>{
+  fn new(string: &'a str) -> Self{
+    Lines{string, cursor: 0}
   }
 }
 
-/// Read binary file into `Vec<u8> buffer`.
-fn load_binary_data(data: K, contents_buffer: &mut Vec<u8>) -> Result<(), &'static str>{
-  if let Some(path) = data.get_symbol().unwrap().strip_prefix(':'){
-    if let Ok(file) = OpenOptions::new().read(true).write(false).create(false).open(path){
-      let mut reader=BufReader::new(file);
-      reader.read_to_end(contents_buffer).unwrap();
-      Ok(())
+impl<'a> Iterator for Lines<'a>{
+  type Item = &'a str;
+
+  fn next(&mut self) -> Option<&'a str>{
+    let bytes=self.string.as_bytes();
+    let start=self.cursor;
+    if start >= bytes.len(){
+      return None;
     }
-    else{
-      return Err("no such file\0");
+    let mut cursor=start;
+    while cursor < bytes.len(){
+      match bytes[cursor]{
+        b'\n' => {
+          self.cursor=cursor+1;
+          return Some(&self.string[start..cursor]);
+        },
+        b'\r' => {
+          // CR followed by LF is one delimiter. Otherwise CR alone is a delimiter.
+          self.cursor=if bytes.get(cursor+1) == Some(&b'\n') {cursor+2} else {cursor+1};
+          return Some(&self.string[start..cursor]);
+        },
+        _ => cursor+=1
+      }
     }
+    self.cursor=bytes.len();
+    Some(&self.string[start..])
+  }
+}
+
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+//                          Private Fucntions                           //
+//++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
+
+/// Split text into lines delimited by <CR/LF>, <LF> or <CR>. The last line does not need to be terminated and trailing
+///  empty lines are ignored.
+fn split_lines(string: &str) -> Vec<&str>{
+  let mut lines=Lines::new(string).collect::<Vec<&str>>();
+  while lines.last() == Some(&""){
+    lines.pop();
+  }
+  lines
+}
+
+/// Read ASCII file mapped into memory as `&str`.
+fn load_ascii_data(data: K, contents_buffer: &mut ContentsBuffer) -> Result<&str, &'static str>{
+  match std::str::from_utf8(load_binary_data(data, contents_buffer)?){
+    Ok(string) => Ok(string),
+    Err(_) => Err("invalid UTF-8 data\0")
+  }
+}
+
+/// Map binary file into memory. Decoders work directly over the mapped pages instead of a copy of the file.
+fn load_binary_data(data: K, contents_buffer: &mut ContentsBuffer) -> Result<&[u8], &'static str>{
+  match data.get_symbol()?.strip_prefix(':'){
+    Some(path) => map_file(path, contents_buffer),
+    None => Err("invalid file name - missing ':'\0")
+  }
+}
+
+/// Map a file of a given path into memory held by `contents_buffer`.
+fn map_file<'a>(path: &str, contents_buffer: &'a mut ContentsBuffer) -> Result<&'a [u8], &'static str>{
+  if let Ok(file) = OpenOptions::new().read(true).write(false).create(false).open(path){
+    // Safety: The file must not be modified by other processes while it is being deserialized.
+    match unsafe{Mmap::map(&file)}{
+      Ok(mapped) => {
+        contents_buffer.mapped = Some(mapped);
+        Ok(contents_buffer.mapped.as_deref().unwrap_or(&[]))
+      },
+      Err(_) => Err("failed to map file\0")
+    }
+  }
+  else{
+    Err("no such file\0")
+  }
+}
+
+/// Write bytes into a file of a given path. The file is created if it does not exist and truncated if it exists.
+fn write_file(path: &str, bytes: &[u8]) -> Result<(), &'static str>{
+  if let Ok(file) = OpenOptions::new().write(true).create(true).truncate(true).open(path){
+    let mut writer=BufWriter::new(file);
+    if writer.write_all(bytes).and_then(|_| writer.flush()).is_err(){
+      return Err("failed to write file\0");
+    }
+    Ok(())
+  }
+  else{
+    Err("failed to create file\0")
+  }
+}
+
+/// Write bytes into a file. The file is created if it does not exist and truncated if it exists.
+fn write_data(path: K, bytes: &[u8]) -> Result<(), &'static str>{
+  if let Some(path) = path.get_symbol()?.strip_prefix(':'){
+    write_file(path, bytes)
+  }
+  else{
+    Err("invalid file name - missing ':'\0")
+  }
+}
+
+/// Get keys and values of a q dictionary whose keys are symbols and values are a compound list, e.g. deserialized
+///  configuration. An empty dictionary `()!()` has no keys.
+fn get_dictionary_slices<'a>(dictionary: K) -> Result<(&'a [S], &'a [K]), &'static str>{
+  if dictionary.get_type() != qtype::DICTIONARY{
+    return Err("not a dictionary\0");
+  }
+  let dictionary_slice=dictionary.as_mut_slice::<K>();
+  if dictionary_slice[0].len() == 0 && dictionary_slice[1].len() == 0{
+    return Ok((&[], &[]));
+  }
+  if dictionary_slice[0].get_type() != qtype::SYMBOL_LIST{
+    return Err("expected symbol keys\0");
+  }
+  if dictionary_slice[1].get_type() != qtype::COMPOUND_LIST{
+    return Err("dictionary values must be a compound list\0");
+  }
+  Ok((dictionary_slice[0].as_mut_slice::<S>(), dictionary_slice[1].as_mut_slice::<K>()))
+}
+
+/// Find a value of a given key from a q dictionary whose values are a compound list, e.g. deserialized configuration.
+///  `None` is returned if the key does not exist.
+fn find_dictionary_value(dictionary: K, key: &str) -> Result<Option<K>, &'static str>{
+  let (keys, values)=get_dictionary_slices(dictionary)?;
+  Ok(keys.iter().position(|symbol| S_to_str(*symbol) == key).map(|index| values[index]))
+}
+
+/// Get a value of a given key from a q dictionary whose values are a compound list, e.g. deserialized configuration.
+fn get_dictionary_value(dictionary: K, key: &str) -> Result<K, &'static str>{
+  match find_dictionary_value(dictionary, key)?{
+    Some(value) => Ok(value),
+    None => Err("missing key in dictionary\0")
+  }
+}
+
+/// Check q type and length of a list in a configuration.
+fn check_config_list<'a, T>(list: K, list_type: i8, length: usize, error: &'static str) -> Result<&'a [T], &'static str>{
+  if list.get_type() != list_type || list.len() as usize != length{
+    Err(error)
   }
   else{
-    return Err("invalid file name - missing ':'\0");
+    Ok(list.as_mut_slice::<T>())
+  }
+}
+
+/// Get a list of a given key from a configuration checking its q type and length.
+fn get_config_list<'a, T>(config: K, key: &str, list_type: i8, length: usize, error: &'static str) -> Result<&'a [T], &'static str>{
+  check_config_list(get_dictionary_value(config, key)?, list_type, length, error)
+}
+
+/// Build a copy of a q dictionary whose values are a compound list replacing values of given keys. Keys which do not
+///  exist are appended. Only the first occurrence of a key is replaced. Given values are owned by the new dictionary and
+///  released if the dictionary is invalid.
+fn update_dictionary(dictionary: K, updates: Vec<(&str, K)>) -> Result<K, &'static str>{
+  let (dictionary_keys, dictionary_values) = match get_dictionary_slices(dictionary){
+    Ok(slices) => slices,
+    Err(error) => {
+      updates.into_iter().for_each(|(_, value)| {decrement_reference_count(value);});
+      return Err(error);
+    }
+  };
+  let mut keys = new_list(qtype::SYMBOL_LIST, 0);
+  let mut values = new_list(qtype::COMPOUND_LIST, 0);
+  let mut updates = updates;
+  for (key, value) in dictionary_keys.iter().zip(dictionary_values.iter()){
+    keys.push_symbol(S_to_str(*key)).unwrap();
+    match updates.iter().position(|(updated, _)| *updated == S_to_str(*key)){
+      Some(index) => values.push(updates.remove(index).1).unwrap(),
+      None => values.push(increment_reference_count(*value)).unwrap()
+    };
+  }
+  for (key, value) in updates{
+    keys.push_symbol(key).unwrap();
+    values.push(value).unwrap();
+  }
+  Ok(new_dictionary(keys, values))
+}
+
+/// Build a q error from a message made at runtime. The message is copied into a buffer of this thread so that it
+///  outlives this function. A message longer than `MAX_RUNTIME_ERROR_LENGTH` is truncated at a character boundary.
+fn new_runtime_error(message: &str) -> K{
+  let mut length = message.len().min(MAX_RUNTIME_ERROR_LENGTH);
+  while !message.is_char_boundary(length){
+    length -= 1;
+  }
+  RUNTIME_ERROR_MESSAGE.with(|buffer|{
+    // Safety: The buffer is touched only on this thread and no reference to it is kept after this call.
+    let buffer = unsafe{&mut *buffer.get()};
+    buffer[..length].copy_from_slice(&message.as_bytes()[..length]);
+    buffer[length] = b'\0';
+    // Safety: The message is truncated at a character boundary.
+    new_error(unsafe{std::str::from_utf8_unchecked(&buffer[..=length])})
+  })
+}
+
+/// Run the body of an interface function converting a panic into a q error. A panic must not unwind across the C ABI
+///  as it aborts the q process.
+fn catch_panic<F>(function: F) -> K where F: FnOnce() -> K{
+  match catch_unwind(AssertUnwindSafe(function)){
+    Ok(result) => result,
+    Err(payload) => {
+      let message = match (payload.downcast_ref::<&str>(), payload.downcast_ref::<String>()){
+        (Some(message), _) => message.to_string(),
+        (_, Some(message)) => message.clone(),
+        _ => "unknown cause".to_string()
+      };
+      new_runtime_error(&format!("unexpected failure - {}", message))
+    }
+  }
+}
+
+/// Get a bool option from a q dictionary of options. `default` is returned if the option is not specified.
+fn get_bool_option(options: K, key: &str, default: bool) -> Result<bool, &'static str>{
+  match find_dictionary_value(options, key)?{
+    Some(value) => value.get_bool(),
+    None => Ok(default)
+  }
+}
+
+/// Get an integral option given by int or long from a q dictionary of options. `default` is returned if the option is
+///  not specified.
+fn get_long_option(options: K, key: &str, default: i64) -> Result<i64, &'static str>{
+  match find_dictionary_value(options, key)?{
+    Some(value) => match value.get_type(){
+      qtype::INT_ATOM => value.get_int().map(|value| value as i64),
+      qtype::LONG_ATOM => value.get_long(),
+      _ => Err("invalid option - expected int or long\0")
+    },
+    None => Ok(default)
+  }
+}
+
+/// Get a symbol option from a q dictionary of options. `default` is returned if the option is not specified.
+fn get_symbol_option(options: K, key: &str, default: &str) -> Result<String, &'static str>{
+  match find_dictionary_value(options, key)?{
+    Some(value) => value.get_symbol().map(|symbol| symbol.to_string()),
+    None => Ok(default.to_string())
   }
 }
 
@@ -126,6 +485,9 @@ fn load_binary_data(data: K, contents_buffer: &mut Vec<u8>) -> Result<(), &'stat
 //                            Load Modules                              //
 //++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++++//
 
+pub mod cff;
 pub mod config;
+pub mod convert;
 pub mod data;
+pub mod header;
 pub mod info;